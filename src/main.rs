@@ -1,10 +1,27 @@
 use bevy::prelude::*;
 use bevy_inspector_egui::bevy_egui::EguiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use uistuff::config::STANDARD_STYLE;
+use uistuff::audio::AudioSettingsPlugin;
+use uistuff::checkbox::CheckboxPlugin;
+use uistuff::input_map::{ActionInput, DEFAULT_BINDINGS, InputMapPlugin};
 use uistuff::main_menu::{MainMenu, MainMenuAction, MainMenuEvent, MainMenuItem, MainMenuPlugin};
+use uistuff::modal::ModalPlugin;
+use uistuff::multi_select::MultiSelectPlugin;
+use uistuff::persistence::SettingsStoragePlugin;
+use uistuff::popup::PopupPlugin;
+use uistuff::radio::RadioPlugin;
+use uistuff::searchable_combobox::SearchableComboboxPlugin;
 use uistuff::settings::SettingsPlugin;
+use uistuff::slider::SliderPlugin;
+use uistuff::spin_box::SpinBoxPlugin;
+use uistuff::staging::StagingPlugin;
+use uistuff::tabs::TabsPlugin;
+use uistuff::text_input::TextInputPlugin;
+use uistuff::theme::ThemePlugin;
+use uistuff::toast::ToastPlugin;
+use uistuff::tooltip::TooltipPlugin;
 use uistuff::utils::UiUtilsPlugin;
+use uistuff::video::VideoSettingsPlugin;
 mod uistuff;
 
 #[derive(States, Clone, Eq, PartialEq, Hash, Debug, Default)]
@@ -18,6 +35,16 @@ fn spawn_camera(mut comms: Commands) {
     comms.spawn(Camera2d);
 }
 
+/// Stands in for game code: logs actions as they are triggered,
+/// so rebinding them on the settings page can be tried out.
+fn log_actions(actions: ActionInput) {
+    for (action, _) in DEFAULT_BINDINGS {
+        if actions.just_pressed(action) {
+            info!("Action {action}");
+        }
+    }
+}
+
 fn on_menu_event(
     mut events: EventReader<MainMenuEvent<u32>>,
     mut exit_events: EventWriter<AppExit>,
@@ -34,11 +61,33 @@ fn on_menu_event(
 
 fn main() {
     App::new()
-        .add_plugins(DefaultPlugins)
+        .add_plugins(DefaultPlugins.set(AssetPlugin {
+            // Reloads theme files as they are edited in `--features dev` builds.
+            watch_for_changes_override: Some(cfg!(feature = "dev")),
+            ..Default::default()
+        }))
         .add_plugins(UiUtilsPlugin)
+        .add_plugins(ThemePlugin::from_args())
+        .add_plugins(PopupPlugin)
+        .add_plugins(ModalPlugin)
+        .add_plugins(ToastPlugin)
+        .add_plugins(TooltipPlugin)
+        .add_plugins(SliderPlugin)
+        .add_plugins(CheckboxPlugin)
+        .add_plugins(TextInputPlugin)
+        .add_plugins(SpinBoxPlugin)
+        .add_plugins(RadioPlugin)
+        .add_plugins(TabsPlugin)
         .add_plugins(EguiPlugin::default())
         .add_plugins(WorldInspectorPlugin::new())
+        .add_plugins(SettingsStoragePlugin)
         .add_plugins(SettingsPlugin)
+        .add_plugins(SearchableComboboxPlugin)
+        .add_plugins(MultiSelectPlugin)
+        .add_plugins(StagingPlugin)
+        .add_plugins(VideoSettingsPlugin)
+        .add_plugins(AudioSettingsPlugin)
+        .add_plugins(InputMapPlugin)
         // .add_plugins(MainMenuPlugin {
         //     menu_state: AppState::MainMenu,
         //     menu: MainMenu(vec![
@@ -71,8 +120,7 @@ fn main() {
         // })
         .init_state::<AppState>()
         .add_systems(Startup, spawn_camera)
+        .add_systems(Update, log_actions)
         // .add_systems(Update, on_menu_event.run_if(on_event::<MainMenuEvent<u32>>))
-        .insert_resource(ClearColor(STANDARD_STYLE.back_color))
-        .insert_resource(STANDARD_STYLE)
         .run();
 }
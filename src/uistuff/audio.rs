@@ -0,0 +1,475 @@
+use crate::uistuff::utils::*;
+use bevy::{audio::Volume, ecs::relationship::RelatedSpawnerCommands, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::UiStyle,
+    multi_select::{MultiSelectChanged, SelectedItems, spawn_multi_select},
+    settings::{DropdownMenu, DropdownMenuItem},
+    slider::{Slider, SliderChanged, spawn_slider},
+    staging::{Modified, RevertField, Staged, settings_row},
+};
+
+/// Volume sliders show percentages in steps of this size.
+const VOLUME_STEP_PERCENT: f32 = 5.0;
+
+/// Linear volumes in `0.0..=1.0`, and the categories that are muted.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub effects: f32,
+    pub mute_music: bool,
+    pub mute_effects: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            music: 0.8,
+            effects: 1.0,
+            mute_music: false,
+            mute_effects: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn category_volume(&self, category: AudioCategory) -> f32 {
+        match category {
+            AudioCategory::Music => self.music,
+            AudioCategory::Effects => self.effects,
+        }
+    }
+
+    pub fn is_muted(&self, category: AudioCategory) -> bool {
+        match category {
+            AudioCategory::Music => self.mute_music,
+            AudioCategory::Effects => self.mute_effects,
+        }
+    }
+
+    pub fn set_muted(&mut self, category: AudioCategory, muted: bool) {
+        match category {
+            AudioCategory::Music => self.mute_music = muted,
+            AudioCategory::Effects => self.mute_effects = muted,
+        }
+    }
+
+    /// The volume playback tagged with `category` ends up at.
+    pub fn effective_volume(&self, category: AudioCategory) -> f32 {
+        if self.is_muted(category) {
+            0.0
+        } else {
+            self.master * self.category_volume(category)
+        }
+    }
+
+    /// The volume untagged playback at `volume` ends up at.
+    pub fn untagged_volume(&self, volume: Volume) -> Volume {
+        volume * Volume::Linear(self.master)
+    }
+}
+
+/// Tags an audio playback entity so its volume follows the matching [`AudioSettings`] value.
+/// The master volume times the category volume replaces the volume in [`PlaybackSettings`].
+/// Untagged playback only follows the master volume, through [`GlobalVolume`]
+/// for new sinks and by setting the volume of sinks that are already playing.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+#[require(PlaybackSettings)]
+pub enum AudioCategory {
+    Music,
+    Effects,
+}
+
+impl AudioCategory {
+    const ALL: [AudioCategory; 2] = [Self::Music, Self::Effects];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Music => "Music",
+            Self::Effects => "Effects",
+        }
+    }
+}
+
+/// Marks the dropdown choosing the muted [`AudioCategory`]s, and its settings row.
+#[derive(Component, Clone, Copy, Debug)]
+struct MutedCategories;
+
+/// Indices into [`AudioCategory::ALL`] of the muted categories.
+fn muted_items(settings: &AudioSettings) -> SelectedItems {
+    SelectedItems(
+        AudioCategory::ALL
+            .into_iter()
+            .enumerate()
+            .filter(|(_, category)| settings.is_muted(*category))
+            .map(|(index, _)| index)
+            .collect(),
+    )
+}
+
+/// Marks a slider that edits one field of [`AudioSettings`].
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioSettingField {
+    Master,
+    Music,
+    Effects,
+}
+
+impl AudioSettingField {
+    const ALL: [AudioSettingField; 3] = [Self::Master, Self::Music, Self::Effects];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Master => "Master volume",
+            Self::Music => "Music volume",
+            Self::Effects => "Effects volume",
+        }
+    }
+
+    fn get(self, settings: &AudioSettings) -> f32 {
+        match self {
+            Self::Master => settings.master,
+            Self::Music => settings.music,
+            Self::Effects => settings.effects,
+        }
+    }
+
+    fn set(self, settings: &mut AudioSettings, value: f32) {
+        match self {
+            Self::Master => settings.master = value,
+            Self::Music => settings.music = value,
+            Self::Effects => settings.effects = value,
+        }
+    }
+}
+
+fn volume_slider(volume: f32) -> Slider {
+    Slider::new(0.0, 100.0, VOLUME_STEP_PERCENT, volume * 100.0)
+}
+
+/// Spawns one settings row per volume control and one for muting categories,
+/// starting at grid row `first_row`.
+pub fn spawn_audio_settings(
+    parent: &mut RelatedSpawnerCommands<ChildOf>,
+    ui_assets: &UIAssets,
+    ui_style: &UiStyle,
+    settings: &AudioSettings,
+    first_row: i16,
+) {
+    let font = ui_assets
+        .font
+        .clone()
+        .expect("Font should be loaded already");
+    for (field, row) in AudioSettingField::ALL.into_iter().zip(first_row..) {
+        let modifier = settings_row(parent, field.label(), field, font.clone(), row, ui_style);
+        spawn_slider(
+            &mut *parent,
+            (field, Name::new(field.label())),
+            volume_slider(field.get(settings)),
+            true,
+            modifier,
+            ui_assets,
+            ui_style,
+        );
+    }
+    let row = first_row + AudioSettingField::ALL.len() as i16;
+    let modifier = settings_row(parent, "Muted", MutedCategories, font, row, ui_style);
+    let menu = DropdownMenu {
+        items: AudioCategory::ALL
+            .into_iter()
+            .map(|category| DropdownMenuItem {
+                label: category.label().to_string(),
+            })
+            .collect(),
+        ..Default::default()
+    };
+    spawn_multi_select(
+        &mut *parent,
+        (MutedCategories, Name::new("Muted")),
+        menu,
+        muted_items(settings).0,
+        modifier,
+        ui_assets,
+        ui_style,
+    );
+}
+
+fn on_audio_slider_changed(
+    mut events: EventReader<SliderChanged>,
+    fields: Query<&AudioSettingField>,
+    mut staged: ResMut<Staged<AudioSettings>>,
+) {
+    for event in events.read() {
+        let Ok(field) = fields.get(event.slider) else {
+            continue;
+        };
+        let mut new_settings = staged.0;
+        field.set(&mut new_settings, event.value / 100.0);
+        staged.set_if_neq(Staged(new_settings));
+    }
+}
+
+fn on_muted_categories_changed(
+    mut events: EventReader<MultiSelectChanged>,
+    dropdowns: Query<(), With<MutedCategories>>,
+    mut staged: ResMut<Staged<AudioSettings>>,
+) {
+    for event in events.read() {
+        if !dropdowns.contains(event.dropdown) {
+            continue;
+        }
+        let mut new_settings = staged.0;
+        for (index, category) in AudioCategory::ALL.into_iter().enumerate() {
+            new_settings.set_muted(category, event.selected.contains(&index));
+        }
+        staged.set_if_neq(Staged(new_settings));
+    }
+}
+
+fn on_audio_field_reverted(
+    mut events: EventReader<RevertField>,
+    fields: Query<&AudioSettingField>,
+    muted_rows: Query<(), With<MutedCategories>>,
+    settings: Res<AudioSettings>,
+    mut staged: ResMut<Staged<AudioSettings>>,
+) {
+    for event in events.read() {
+        let mut new_settings = staged.0;
+        if let Ok(field) = fields.get(event.entity) {
+            field.set(&mut new_settings, field.get(&settings));
+        } else if muted_rows.contains(event.entity) {
+            for category in AudioCategory::ALL {
+                new_settings.set_muted(category, settings.is_muted(category));
+            }
+        } else {
+            continue;
+        }
+        staged.set_if_neq(Staged(new_settings));
+    }
+}
+
+fn sync_audio_widgets(
+    staged: Res<Staged<AudioSettings>>,
+    widgets: Query<(&AudioSettingField, &mut Slider)>,
+    muted_dropdowns: Query<&mut SelectedItems, With<MutedCategories>>,
+) {
+    for (field, mut slider) in widgets {
+        let value = slider.snap(field.get(&staged) * 100.0);
+        if slider.value != value {
+            slider.value = value;
+        }
+    }
+    for mut selected in muted_dropdowns {
+        selected.set_if_neq(muted_items(&staged));
+    }
+}
+
+fn update_audio_modified(
+    settings: Res<AudioSettings>,
+    staged: Res<Staged<AudioSettings>>,
+    entries: Query<(&AudioSettingField, &mut Modified)>,
+    muted_entries: Query<&mut Modified, (With<MutedCategories>, Without<AudioSettingField>)>,
+) {
+    for (field, mut modified) in entries {
+        modified.set_if_neq(Modified(field.get(&settings) != field.get(&staged)));
+    }
+    for mut modified in muted_entries {
+        modified.set_if_neq(Modified(muted_items(&settings) != muted_items(&staged)));
+    }
+}
+
+/// [`GlobalVolume`] only counts when a sink starts, so untagged sinks that are
+/// already playing are set to their [`PlaybackSettings`] volume times the master volume.
+fn apply_global_volume(
+    settings: Res<AudioSettings>,
+    mut global: ResMut<GlobalVolume>,
+    sinks: Query<(&PlaybackSettings, &mut AudioSink), Without<AudioCategory>>,
+) {
+    global.volume = Volume::Linear(settings.master);
+    for (playback, mut sink) in sinks {
+        sink.set_volume(settings.untagged_volume(playback.volume));
+    }
+}
+
+/// Sinks that are already playing ignore [`GlobalVolume`] and [`PlaybackSettings`] changes,
+/// so their volume is set directly as well.
+fn apply_category_volumes(
+    settings: Res<AudioSettings>,
+    playback: Query<(
+        Ref<AudioCategory>,
+        &mut PlaybackSettings,
+        Option<&mut AudioSink>,
+    )>,
+) {
+    let settings_changed = settings.is_changed();
+    for (category, mut playback, sink) in playback {
+        if !settings_changed && !category.is_changed() {
+            continue;
+        }
+        let volume = Volume::Linear(settings.effective_volume(*category));
+        playback.volume = volume;
+        if let Some(mut sink) = sink {
+            sink.set_volume(volume);
+        }
+    }
+}
+
+/// New sinks start at their [`PlaybackSettings`] volume times [`GlobalVolume`],
+/// which counts the master volume twice for tagged playback.
+fn fix_started_category_sinks(
+    settings: Res<AudioSettings>,
+    sinks: Query<(&AudioCategory, &mut AudioSink), Added<AudioSink>>,
+) {
+    for (category, mut sink) in sinks {
+        sink.set_volume(Volume::Linear(settings.effective_volume(*category)));
+    }
+}
+
+pub struct AudioSettingsPlugin;
+
+impl Plugin for AudioSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                on_audio_slider_changed.run_if(on_event::<SliderChanged>),
+                on_muted_categories_changed.run_if(on_event::<MultiSelectChanged>),
+                on_audio_field_reverted.run_if(on_event::<RevertField>),
+                apply_global_volume.run_if(resource_changed::<AudioSettings>),
+                sync_audio_widgets.run_if(resource_changed::<Staged<AudioSettings>>),
+                update_audio_modified.run_if(
+                    resource_changed::<AudioSettings>.or(resource_changed::<Staged<AudioSettings>>),
+                ),
+                apply_category_volumes,
+            )
+                .chain(),
+        )
+        // Sinks are started in `PostUpdate`.
+        .add_systems(Last, fix_started_category_sinks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_app(settings: AudioSettings) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(settings)
+            .init_resource::<GlobalVolume>()
+            .add_systems(
+                Update,
+                (
+                    apply_global_volume.run_if(resource_changed::<AudioSettings>),
+                    apply_category_volumes,
+                ),
+            );
+        app
+    }
+
+    fn global_volume(app: &App) -> f32 {
+        app.world().resource::<GlobalVolume>().volume.to_linear()
+    }
+
+    fn playback_volume(app: &App, entity: Entity) -> f32 {
+        app.world()
+            .get::<PlaybackSettings>(entity)
+            .unwrap()
+            .volume
+            .to_linear()
+    }
+
+    #[test]
+    fn volumes_follow_settings() {
+        let mut app = test_app(AudioSettings {
+            master: 0.5,
+            music: 0.8,
+            effects: 0.4,
+            ..Default::default()
+        });
+        let music = app.world_mut().spawn(AudioCategory::Music).id();
+        let effects = app.world_mut().spawn(AudioCategory::Effects).id();
+        app.update();
+        assert_eq!(global_volume(&app), 0.5);
+        assert_eq!(playback_volume(&app, music), 0.5 * 0.8);
+        assert_eq!(playback_volume(&app, effects), 0.5 * 0.4);
+
+        let mut settings = app.world_mut().resource_mut::<AudioSettings>();
+        settings.master = 0.25;
+        settings.effects = 1.0;
+        app.update();
+        assert_eq!(global_volume(&app), 0.25);
+        assert_eq!(playback_volume(&app, music), 0.25 * 0.8);
+        assert_eq!(playback_volume(&app, effects), 0.25);
+    }
+
+    #[test]
+    fn retagged_playback_follows_new_category() {
+        let mut app = test_app(AudioSettings {
+            master: 1.0,
+            music: 0.5,
+            effects: 0.25,
+            ..Default::default()
+        });
+        let entity = app.world_mut().spawn(AudioCategory::Music).id();
+        app.update();
+        assert_eq!(playback_volume(&app, entity), 0.5);
+
+        app.world_mut()
+            .entity_mut(entity)
+            .insert(AudioCategory::Effects);
+        app.update();
+        assert_eq!(playback_volume(&app, entity), 0.25);
+    }
+
+    #[test]
+    fn muted_categories_are_silent() {
+        let mut app = test_app(AudioSettings {
+            mute_effects: true,
+            ..Default::default()
+        });
+        let music = app.world_mut().spawn(AudioCategory::Music).id();
+        let effects = app.world_mut().spawn(AudioCategory::Effects).id();
+        app.update();
+        assert_eq!(playback_volume(&app, music), 0.8);
+        assert_eq!(playback_volume(&app, effects), 0.0);
+
+        let mut settings = app.world_mut().resource_mut::<AudioSettings>();
+        settings.set_muted(AudioCategory::Effects, false);
+        settings.set_muted(AudioCategory::Music, true);
+        app.update();
+        assert_eq!(playback_volume(&app, music), 0.0);
+        assert_eq!(playback_volume(&app, effects), 1.0);
+    }
+
+    #[test]
+    fn untagged_playback_follows_master_volume() {
+        let settings = AudioSettings {
+            master: 0.5,
+            music: 0.25,
+            mute_music: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.untagged_volume(Volume::Linear(0.5)).to_linear(),
+            0.25
+        );
+        assert_eq!(
+            settings.untagged_volume(Volume::Linear(1.0)).to_linear(),
+            0.5
+        );
+    }
+
+    #[test]
+    fn muted_items_follow_settings() {
+        let mut settings = AudioSettings::default();
+        assert!(muted_items(&settings).is_empty());
+        settings.mute_effects = true;
+        assert_eq!(muted_items(&settings).0, [1].into());
+    }
+}
@@ -0,0 +1,479 @@
+use crate::uistuff::utils::*;
+use bevy::{
+    input::{ButtonState, gamepad::GamepadButtonChangedEvent, keyboard::KeyboardInput},
+    input_focus::FocusedInput,
+    prelude::*,
+};
+
+use super::{
+    config::{StyleColor, UiStyle},
+    layouts::NodeModifier,
+};
+
+const BOX_SIZE: f32 = 24.0;
+const MARK_SIZE: f32 = 12.0;
+const SWITCH_WIDTH: f32 = 44.0;
+const KNOB_SIZE: f32 = 18.0;
+const KNOB_MARGIN: f32 = (BOX_SIZE - KNOB_SIZE) / 2.0;
+/// How fast the switch knob travels, in pixels per second.
+const KNOB_SPEED: f32 = 200.0;
+
+/// State of a checkbox or toggle switch.
+/// `Mixed` is only ever set from code, for headers of partially checked groups.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Checked {
+    #[default]
+    Off,
+    On,
+    Mixed,
+}
+
+impl Checked {
+    pub fn is_on(self) -> bool {
+        self == Checked::On
+    }
+
+    fn toggled(self) -> Self {
+        match self {
+            Checked::On => Checked::Off,
+            Checked::Off | Checked::Mixed => Checked::On,
+        }
+    }
+}
+
+impl From<bool> for Checked {
+    fn from(value: bool) -> Self {
+        if value { Checked::On } else { Checked::Off }
+    }
+}
+
+/// Makes a checkbox the header of a group: its state shows whether all, none
+/// or only some of the members are checked, and toggling it sets all members.
+/// Multi-select dropdowns use one for their "All" item.
+#[derive(Component, Clone, Debug, Default)]
+pub struct CheckboxGroup {
+    pub members: Vec<Entity>,
+}
+
+/// Sent when a checkbox or toggle switch changes state through user input.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CheckboxChanged {
+    pub checkbox: Entity,
+    pub checked: Checked,
+}
+
+#[derive(Component, Default)]
+#[require(Checked, Focusable, Interaction, Button)]
+pub struct Checkbox;
+
+#[derive(Component)]
+struct CheckMark;
+
+/// The moving part of a toggle switch, sliding towards `target_left`.
+#[derive(Component)]
+struct ToggleKnob {
+    target_left: f32,
+}
+
+fn knob_left(checked: Checked) -> f32 {
+    match checked {
+        Checked::On => SWITCH_WIDTH - KNOB_SIZE - KNOB_MARGIN,
+        Checked::Off => KNOB_MARGIN,
+        Checked::Mixed => (SWITCH_WIDTH - KNOB_SIZE) / 2.0,
+    }
+}
+
+fn mark_node(checked: Checked) -> Node {
+    Node {
+        display: if checked == Checked::Off {
+            Display::None
+        } else {
+            Display::Flex
+        },
+        width: Val::Px(MARK_SIZE),
+        height: Val::Px(if checked == Checked::Mixed {
+            MARK_SIZE / 3.0
+        } else {
+            MARK_SIZE
+        }),
+        ..Default::default()
+    }
+}
+
+fn checkbox_label(text: impl Into<String>, font: Handle<Font>, style: &UiStyle) -> impl Bundle {
+    (
+        Text::new(text),
+        StyleColor::HoverFore.text(style),
+        TextFont {
+            font,
+            font_size: style.font_sizes.body,
+            ..Default::default()
+        },
+    )
+}
+
+fn checkbox_root(node_modifier: NodeModifier, checked: Checked, style: &UiStyle) -> impl Bundle {
+    (
+        node_modifier.modify(Node {
+            display: Display::Flex,
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(style.spacing.medium),
+            padding: UiRect::all(Val::Px(style.spacing.small)),
+            ..Default::default()
+        }),
+        Checkbox,
+        checked,
+    )
+}
+
+/// The square of a checkbox showing `checked`, without any interaction.
+pub fn check_mark_box(checked: Checked, style: &UiStyle) -> impl Bundle {
+    (
+        Node {
+            width: Val::Px(BOX_SIZE),
+            height: Val::Px(BOX_SIZE),
+            border: UiRect::all(Val::Px(style.borders.normal)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        StyleColor::NormalBack.border(style),
+        StyleColor::NormalFore.background(style),
+        children![(
+            CheckMark,
+            mark_node(checked),
+            StyleColor::NormalBack.background(style),
+        )],
+    )
+}
+
+/// A square checkbox followed by a label.
+pub fn checkbox_box(
+    text: impl Into<String>,
+    font: Handle<Font>,
+    node_modifier: NodeModifier,
+    checked: Checked,
+    style: &UiStyle,
+) -> impl Bundle {
+    (
+        checkbox_root(node_modifier, checked, style),
+        children![
+            check_mark_box(checked, style),
+            checkbox_label(text, font, style),
+        ],
+    )
+}
+
+/// A sliding on/off switch followed by a label.
+pub fn toggle_switch_box(
+    text: impl Into<String>,
+    font: Handle<Font>,
+    node_modifier: NodeModifier,
+    checked: Checked,
+    style: &UiStyle,
+) -> impl Bundle {
+    (
+        checkbox_root(node_modifier, checked, style),
+        children![
+            (
+                Node {
+                    width: Val::Px(SWITCH_WIDTH),
+                    height: Val::Px(BOX_SIZE),
+                    ..Default::default()
+                },
+                BorderRadius::MAX,
+                BackgroundColor(switch_track_color(checked, style)),
+                children![(
+                    ToggleKnob {
+                        target_left: knob_left(checked),
+                    },
+                    Node {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(KNOB_MARGIN),
+                        left: Val::Px(knob_left(checked)),
+                        width: Val::Px(KNOB_SIZE),
+                        height: Val::Px(KNOB_SIZE),
+                        ..Default::default()
+                    },
+                    BorderRadius::MAX,
+                    StyleColor::NormalFore.background(style),
+                )],
+            ),
+            checkbox_label(text, font, style),
+        ],
+    )
+}
+
+fn switch_track_color(checked: Checked, style: &UiStyle) -> Color {
+    match checked {
+        Checked::On => style.button_style.normal_colors.back_color,
+        Checked::Off | Checked::Mixed => style.button_style.hover_colors.back_color,
+    }
+}
+
+/// Toggles a checkbox and, if it heads a group, sets the members to match.
+fn toggle_checkbox(
+    entity: Entity,
+    checkboxes: &mut Query<(&mut Checked, Option<&CheckboxGroup>), With<Checkbox>>,
+    changed: &mut EventWriter<CheckboxChanged>,
+) {
+    let Ok((mut checked, group)) = checkboxes.get_mut(entity) else {
+        return;
+    };
+    let new_state = checked.toggled();
+    *checked = new_state;
+    changed.write(CheckboxChanged {
+        checkbox: entity,
+        checked: new_state,
+    });
+    let members = group.map(|g| g.members.clone()).unwrap_or_default();
+    for member in members {
+        if let Ok((mut member_checked, _)) = checkboxes.get_mut(member)
+            && member_checked.set_if_neq(new_state)
+        {
+            changed.write(CheckboxChanged {
+                checkbox: member,
+                checked: new_state,
+            });
+        }
+    }
+}
+
+fn on_checkbox_clicked(
+    mut trigger: Trigger<Pointer<Click>>,
+    mut checkboxes: Query<(&mut Checked, Option<&CheckboxGroup>), With<Checkbox>>,
+    mut changed: EventWriter<CheckboxChanged>,
+) {
+    let entity = trigger.target();
+    if trigger.event().button != PointerButton::Primary || !checkboxes.contains(entity) {
+        return;
+    }
+    trigger.propagate(false);
+    toggle_checkbox(entity, &mut checkboxes, &mut changed);
+}
+
+fn on_checkbox_key(
+    mut trigger: Trigger<FocusedInput<KeyboardInput>>,
+    mut checkboxes: Query<(&mut Checked, Option<&CheckboxGroup>), With<Checkbox>>,
+    mut changed: EventWriter<CheckboxChanged>,
+) {
+    let entity = trigger.target();
+    let input = &trigger.event().input;
+    if input.state != ButtonState::Pressed
+        || !matches!(input.key_code, KeyCode::Space | KeyCode::Enter)
+        || !checkboxes.contains(entity)
+    {
+        return;
+    }
+    trigger.propagate(false);
+    toggle_checkbox(entity, &mut checkboxes, &mut changed);
+}
+
+fn on_checkbox_gamepad_button(
+    mut trigger: Trigger<FocusedInput<GamepadButtonChangedEvent>>,
+    mut checkboxes: Query<(&mut Checked, Option<&CheckboxGroup>), With<Checkbox>>,
+    mut changed: EventWriter<CheckboxChanged>,
+) {
+    let entity = trigger.target();
+    let input = &trigger.event().input;
+    if input.state != ButtonState::Pressed
+        || input.button != GamepadButton::South
+        || !checkboxes.contains(entity)
+    {
+        return;
+    }
+    trigger.propagate(false);
+    toggle_checkbox(entity, &mut checkboxes, &mut changed);
+}
+
+/// The state of a group header: the state shared by all members, or `Mixed`.
+/// `None` for a group without members.
+fn group_state(mut states: impl Iterator<Item = Checked>) -> Option<Checked> {
+    let first = states.next()?;
+    Some(states.fold(
+        first,
+        |acc, state| {
+            if acc == state { acc } else { Checked::Mixed }
+        },
+    ))
+}
+
+fn update_group_headers(
+    mut headers: Query<(&CheckboxGroup, &mut Checked)>,
+    members: Query<&Checked, Without<CheckboxGroup>>,
+) {
+    for (group, mut checked) in headers.iter_mut() {
+        if let Some(state) = group_state(members.iter_many(&group.members).copied()) {
+            checked.set_if_neq(state);
+        }
+    }
+}
+
+/// Also repaints switch tracks when the style changes, their colour depends on the state.
+fn update_checkbox_visuals(
+    checkboxes: Query<(Entity, Ref<Checked>), With<Checkbox>>,
+    children_query: Query<&Children>,
+    mut marks: Query<&mut Node, (With<CheckMark>, Without<ToggleKnob>)>,
+    mut knobs: Query<(&mut ToggleKnob, &ChildOf)>,
+    mut backgrounds: Query<&mut BackgroundColor>,
+    ui_style: Res<UiStyle>,
+) {
+    for (entity, checked) in checkboxes {
+        if !checked.is_changed() && !ui_style.is_changed() {
+            continue;
+        }
+        for descendant in children_query.iter_descendants(entity) {
+            if let Ok(mut mark) = marks.get_mut(descendant) {
+                *mark = mark_node(*checked);
+            }
+            if let Ok((mut knob, child_of)) = knobs.get_mut(descendant) {
+                knob.target_left = knob_left(*checked);
+                if let Ok(mut track) = backgrounds.get_mut(child_of.parent()) {
+                    track.0 = switch_track_color(*checked, &ui_style);
+                }
+            }
+        }
+    }
+}
+
+fn animate_toggle_knobs(time: Res<Time>, knobs: Query<(&ToggleKnob, &mut Node)>) {
+    let max_step = KNOB_SPEED * time.delta_secs();
+    for (knob, mut node) in knobs {
+        let Val::Px(left) = node.left else {
+            continue;
+        };
+        if left != knob.target_left {
+            let step = (knob.target_left - left).clamp(-max_step, max_step);
+            node.left = Val::Px(left + step);
+        }
+    }
+}
+
+pub struct CheckboxPlugin;
+
+impl Plugin for CheckboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CheckboxChanged>()
+            .add_observer(on_checkbox_clicked)
+            .add_observer(on_checkbox_key)
+            .add_observer(on_checkbox_gamepad_button)
+            .add_systems(
+                Update,
+                (
+                    update_group_headers,
+                    update_checkbox_visuals,
+                    animate_toggle_knobs,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uistuff::utils::test_input::click;
+
+    #[test]
+    fn toggled() {
+        assert_eq!(Checked::Off.toggled(), Checked::On);
+        assert_eq!(Checked::On.toggled(), Checked::Off);
+        assert_eq!(Checked::Mixed.toggled(), Checked::On);
+    }
+
+    #[test]
+    fn group_state_folds_member_states() {
+        use Checked::*;
+        assert_eq!(group_state([].into_iter()), None);
+        assert_eq!(group_state([On, On].into_iter()), Some(On));
+        assert_eq!(group_state([Off, Off, Off].into_iter()), Some(Off));
+        assert_eq!(group_state([On, Off].into_iter()), Some(Mixed));
+        assert_eq!(group_state([Off, On, On].into_iter()), Some(Mixed));
+    }
+
+    fn test_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, CheckboxPlugin))
+            .insert_resource(UiStyle::default());
+        app
+    }
+
+    /// A group header and its members, with the members in `states`.
+    fn spawn_group(app: &mut App, states: &[Checked]) -> (Entity, Vec<Entity>) {
+        let world = app.world_mut();
+        let members: Vec<Entity> = states
+            .iter()
+            .map(|state| world.spawn((Checkbox, *state)).id())
+            .collect();
+        let header = world
+            .spawn((
+                Checkbox,
+                CheckboxGroup {
+                    members: members.clone(),
+                },
+            ))
+            .id();
+        (header, members)
+    }
+
+    fn states(app: &App, entities: &[Entity]) -> Vec<Checked> {
+        entities
+            .iter()
+            .map(|e| *app.world().get::<Checked>(*e).unwrap())
+            .collect()
+    }
+
+    fn sent_changes(app: &App) -> Vec<(Entity, Checked)> {
+        let events = app.world().resource::<Events<CheckboxChanged>>();
+        events
+            .get_cursor()
+            .read(events)
+            .map(|event| (event.checkbox, event.checked))
+            .collect()
+    }
+
+    #[test]
+    fn header_follows_members() {
+        let mut app = test_app();
+        let (header, members) = spawn_group(&mut app, &[Checked::On, Checked::Off]);
+        app.update();
+        assert_eq!(states(&app, &[header]), [Checked::Mixed]);
+
+        app.world_mut().entity_mut(members[1]).insert(Checked::On);
+        app.update();
+        assert_eq!(states(&app, &[header]), [Checked::On]);
+    }
+
+    #[test]
+    fn clicking_a_header_sets_all_members() {
+        let mut app = test_app();
+        let (header, members) = spawn_group(&mut app, &[Checked::On, Checked::Off]);
+        app.update();
+
+        click(&mut app, header);
+        assert_eq!(states(&app, &members), [Checked::On, Checked::On]);
+        assert_eq!(
+            sent_changes(&app),
+            [(header, Checked::On), (members[1], Checked::On)]
+        );
+
+        app.update();
+        click(&mut app, header);
+        app.update();
+        assert_eq!(states(&app, &[header]), [Checked::Off]);
+        assert_eq!(states(&app, &members), [Checked::Off, Checked::Off]);
+    }
+
+    #[test]
+    fn clicking_a_member_only_toggles_it() {
+        let mut app = test_app();
+        let (header, members) = spawn_group(&mut app, &[Checked::Off, Checked::Off]);
+        app.update();
+
+        click(&mut app, members[0]);
+        app.update();
+        assert_eq!(states(&app, &members), [Checked::On, Checked::Off]);
+        assert_eq!(states(&app, &[header]), [Checked::Mixed]);
+    }
+}
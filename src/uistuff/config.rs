@@ -2,34 +2,465 @@ use bevy::{
     color::palettes::{css::*, tailwind::*},
     prelude::*,
 };
-#[derive(Clone, Copy, Debug)]
+use serde::Deserialize;
+
+use super::{theme::deserialize_color, toast::Severity};
+
+/// Colours of a widget in one state. The border is transparent unless given.
+#[derive(Clone, Copy, Debug, Deserialize)]
 pub struct BackgroundForeground {
+    #[serde(deserialize_with = "deserialize_color")]
     pub back_color: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub fore_color: Color,
+    #[serde(default = "no_border", deserialize_with = "deserialize_color")]
+    pub border_color: Color,
+}
+
+fn no_border() -> Color {
+    Color::NONE
+}
+
+impl BackgroundForeground {
+    pub const fn new(back_color: Color, fore_color: Color) -> Self {
+        Self {
+            back_color,
+            fore_color,
+            border_color: Color::NONE,
+        }
+    }
+}
+
+/// Interaction state a widget is drawn in, kept up to date by
+/// [`super::utils::update_visual_states`] for widgets that react to input.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[require(Interaction)]
+pub enum VisualState {
+    #[default]
+    Normal,
+    Hovered,
+    Pressed,
+    Focused,
+    Disabled,
+    Selected,
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Colours of a widget in each [`VisualState`].
+///
+/// Theme files only need `normal_colors`, the other states are derived from
+/// the normal and hover colours when left out, see [`ButtonStyle::derive`].
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(from = "ButtonStyleStates")]
 pub struct ButtonStyle {
     pub normal_colors: BackgroundForeground,
     pub hover_colors: BackgroundForeground,
+    pub pressed_colors: BackgroundForeground,
+    pub focused_colors: BackgroundForeground,
+    pub disabled_colors: BackgroundForeground,
+    pub selected_colors: BackgroundForeground,
+}
+
+/// A [`ButtonStyle`] as written in theme files, where states can be left out.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ButtonStyleStates {
+    pub normal_colors: Option<BackgroundForeground>,
+    pub hover_colors: Option<BackgroundForeground>,
+    pub pressed_colors: Option<BackgroundForeground>,
+    pub focused_colors: Option<BackgroundForeground>,
+    pub disabled_colors: Option<BackgroundForeground>,
+    pub selected_colors: Option<BackgroundForeground>,
+}
+
+impl From<ButtonStyleStates> for ButtonStyle {
+    fn from(value: ButtonStyleStates) -> Self {
+        ButtonStyle::derive(value)
+    }
+}
+
+impl ButtonStyle {
+    /// Fills in the states left out of `states`:
+    /// - normal falls back to the standard button colours,
+    /// - hovered lightens the normal background,
+    /// - pressed darkens the hovered background,
+    /// - focused is normal with a border in the text colour,
+    /// - disabled is normal with faded text,
+    /// - selected is hovered with a border in the text colour.
+    pub fn derive(states: ButtonStyleStates) -> Self {
+        let normal = states
+            .normal_colors
+            .unwrap_or_else(|| UiStyle::default().button_style.normal_colors);
+        let hover = states.hover_colors.unwrap_or(BackgroundForeground {
+            back_color: normal.back_color.lighter(0.1),
+            ..normal
+        });
+        let pressed = states.pressed_colors.unwrap_or(BackgroundForeground {
+            back_color: hover.back_color.darker(0.15),
+            ..hover
+        });
+        let focused = states.focused_colors.unwrap_or(BackgroundForeground {
+            border_color: normal.fore_color,
+            ..normal
+        });
+        let disabled = states.disabled_colors.unwrap_or(BackgroundForeground {
+            fore_color: normal
+                .fore_color
+                .with_alpha(normal.fore_color.alpha() * 0.5),
+            ..normal
+        });
+        let selected = states.selected_colors.unwrap_or(BackgroundForeground {
+            border_color: hover.fore_color,
+            ..hover
+        });
+        Self {
+            normal_colors: normal,
+            hover_colors: hover,
+            pressed_colors: pressed,
+            focused_colors: focused,
+            disabled_colors: disabled,
+            selected_colors: selected,
+        }
+    }
+
+    /// A style with the given normal and hover colours and the other states derived.
+    pub fn new(normal_colors: BackgroundForeground, hover_colors: BackgroundForeground) -> Self {
+        Self::derive(ButtonStyleStates {
+            normal_colors: Some(normal_colors),
+            hover_colors: Some(hover_colors),
+            ..Default::default()
+        })
+    }
+
+    /// A style that looks the same in every state.
+    pub fn uniform(colors: BackgroundForeground) -> Self {
+        Self {
+            normal_colors: colors,
+            hover_colors: colors,
+            pressed_colors: colors,
+            focused_colors: colors,
+            disabled_colors: colors,
+            selected_colors: colors,
+        }
+    }
+
+    pub fn colors(&self, state: VisualState) -> BackgroundForeground {
+        match state {
+            VisualState::Normal => self.normal_colors,
+            VisualState::Hovered => self.hover_colors,
+            VisualState::Pressed => self.pressed_colors,
+            VisualState::Focused => self.focused_colors,
+            VisualState::Disabled => self.disabled_colors,
+            VisualState::Selected => self.selected_colors,
+        }
+    }
+}
+
+/// Colors of notifications by severity.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct SeverityColors {
+    pub info: BackgroundForeground,
+    pub success: BackgroundForeground,
+    pub warning: BackgroundForeground,
+    pub error: BackgroundForeground,
+}
+
+/// Steps of the spacing scale for paddings, margins and gaps, in logical pixels.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct Spacing {
+    pub small: f32,
+    pub medium: f32,
+    pub large: f32,
+}
+
+impl Default for Spacing {
+    fn default() -> Self {
+        Self {
+            small: 5.0,
+            medium: 10.0,
+            large: 20.0,
+        }
+    }
+}
+
+/// Border widths in logical pixels, and the colour of borders and separators
+/// that don't belong to a widget state, like the frames of menus and dialogs.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct Borders {
+    pub thin: f32,
+    pub normal: f32,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub color: Color,
+}
+
+impl Default for Borders {
+    fn default() -> Self {
+        Self {
+            thin: 1.0,
+            normal: 2.0,
+            color: Color::Srgba(SILVER),
+        }
+    }
+}
+
+/// Corner radii in logical pixels: small for widgets, medium for panels.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct Radii {
+    pub small: f32,
+    pub medium: f32,
+}
+
+impl Default for Radii {
+    fn default() -> Self {
+        Self {
+            small: 4.0,
+            medium: 8.0,
+        }
+    }
 }
 
-#[derive(Resource, Clone, Copy, Debug)]
+/// Colours of widget details that no [`StyleRole`] covers.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct DetailColors {
+    /// Behind the items of dropdown menus.
+    #[serde(deserialize_with = "deserialize_color")]
+    pub menu_back: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub scroll_track: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub scroll_thumb: Color,
+    /// Characters of dropdown items matching the typed filter.
+    #[serde(deserialize_with = "deserialize_color")]
+    pub matched_text: Color,
+    /// Matching characters of the highlighted dropdown item.
+    #[serde(deserialize_with = "deserialize_color")]
+    pub matched_highlighted_text: Color,
+    /// Labels of settings changed but not applied yet.
+    #[serde(deserialize_with = "deserialize_color")]
+    pub modified_label: Color,
+    /// Borders of fields holding text that isn't a valid value.
+    #[serde(deserialize_with = "deserialize_color")]
+    pub invalid_border: Color,
+    /// Dims the UI behind dialogs.
+    #[serde(deserialize_with = "deserialize_color")]
+    pub overlay: Color,
+}
+
+impl Default for DetailColors {
+    fn default() -> Self {
+        Self {
+            menu_back: Color::Srgba(BLUE),
+            scroll_track: Color::Srgba(MIDNIGHT_BLUE),
+            scroll_thumb: Color::Srgba(AQUAMARINE),
+            matched_text: Color::Srgba(YELLOW),
+            matched_highlighted_text: Color::Srgba(CRIMSON),
+            modified_label: Color::Srgba(DARK_ORANGE),
+            invalid_border: Color::srgb(0.85, 0.1, 0.1),
+            overlay: Color::BLACK.with_alpha(0.5),
+        }
+    }
+}
+
+/// Kinds of text, each with its own size in [`FontSizes`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextRole {
+    Title,
+    Body,
+    Button,
+    Caption,
+}
+
+/// Font sizes per [`TextRole`], in logical pixels.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct FontSizes {
+    pub title: f32,
+    pub body: f32,
+    pub button: f32,
+    pub caption: f32,
+}
+
+impl Default for FontSizes {
+    fn default() -> Self {
+        Self {
+            title: 40.0,
+            body: 32.0,
+            button: 32.0,
+            caption: 24.0,
+        }
+    }
+}
+
+impl FontSizes {
+    pub fn get(&self, role: TextRole) -> f32 {
+        match role {
+            TextRole::Title => self.title,
+            TextRole::Body => self.body,
+            TextRole::Button => self.button,
+            TextRole::Caption => self.caption,
+        }
+    }
+}
+
+/// Colors and design tokens of the whole UI: spacing, borders, corner radii and
+/// font sizes. Themes are loaded into it from files, see [`super::theme`].
+///
+/// Sizes are read when widgets are spawned, colours follow style changes.
+#[derive(Resource, Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
 pub struct UiStyle {
     pub button_style: ButtonStyle,
+    /// Items of dropdown and context menus.
+    pub menu_item_style: ButtonStyle,
+    #[serde(deserialize_with = "deserialize_color")]
     pub back_color: Color,
+    /// Text of setting labels, drawn straight on the background.
+    #[serde(deserialize_with = "deserialize_color")]
+    pub label_color: Color,
+    pub severity_colors: SeverityColors,
+    pub detail_colors: DetailColors,
+    pub spacing: Spacing,
+    pub borders: Borders,
+    pub radii: Radii,
+    pub font_sizes: FontSizes,
 }
 
-pub const STANDARD_STYLE: UiStyle = UiStyle {
-    back_color: Color::Srgba(VIOLET_100),
-    button_style: ButtonStyle {
-        normal_colors: BackgroundForeground {
-            back_color: Color::Srgba(PINK_400),
-            fore_color: Color::Srgba(WHITE),
-        },
-        hover_colors: BackgroundForeground {
-            back_color: Color::Srgba(PINK_200),
-            fore_color: Color::Srgba(GRAY_700),
-        },
-    },
-};
+/// Which colours of the [`UiStyle`] a text box or button uses. Widgets keep their
+/// role rather than the colours, so they are repainted when the style changes.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StyleRole {
+    /// The normal button colours in every state.
+    Normal,
+    /// Like [`StyleRole::Normal`], in the title font size.
+    Title,
+    /// The hover button colours in every state. Used for text that stands out.
+    Highlight,
+    /// Setting labels, without a background.
+    Label,
+    Button,
+    MenuItem,
+    Toast(Severity),
+}
+
+impl StyleRole {
+    /// The colours of this role in `style`. Only buttons and menu items look
+    /// different depending on their [`VisualState`].
+    pub fn colors(self, style: &UiStyle) -> ButtonStyle {
+        match self {
+            StyleRole::Normal | StyleRole::Title => {
+                ButtonStyle::uniform(style.button_style.normal_colors)
+            }
+            StyleRole::Highlight => ButtonStyle::uniform(style.button_style.hover_colors),
+            StyleRole::Label => {
+                ButtonStyle::uniform(BackgroundForeground::new(Color::NONE, style.label_color))
+            }
+            StyleRole::Button => style.button_style,
+            StyleRole::MenuItem => style.menu_item_style,
+            StyleRole::Toast(severity) => ButtonStyle::uniform(severity.colors(style)),
+        }
+    }
+
+    pub fn text_role(self) -> TextRole {
+        match self {
+            StyleRole::Title => TextRole::Title,
+            StyleRole::Button => TextRole::Button,
+            StyleRole::MenuItem => TextRole::Caption,
+            StyleRole::Normal | StyleRole::Highlight | StyleRole::Label | StyleRole::Toast(_) => {
+                TextRole::Body
+            }
+        }
+    }
+
+    pub fn font_size(self, style: &UiStyle) -> f32 {
+        style.font_sizes.get(self.text_role())
+    }
+}
+
+/// A single colour of the [`UiStyle`], for the parts of widgets that aren't
+/// text boxes, like checkbox marks and slider tracks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StyleColor {
+    Background,
+    NormalBack,
+    NormalFore,
+    HoverBack,
+    HoverFore,
+    Border,
+    MenuBack,
+    ScrollTrack,
+    ScrollThumb,
+    Overlay,
+}
+
+impl StyleColor {
+    pub fn get(self, style: &UiStyle) -> Color {
+        match self {
+            StyleColor::Background => style.back_color,
+            StyleColor::NormalBack => style.button_style.normal_colors.back_color,
+            StyleColor::NormalFore => style.button_style.normal_colors.fore_color,
+            StyleColor::HoverBack => style.button_style.hover_colors.back_color,
+            StyleColor::HoverFore => style.button_style.hover_colors.fore_color,
+            StyleColor::Border => style.borders.color,
+            StyleColor::MenuBack => style.detail_colors.menu_back,
+            StyleColor::ScrollTrack => style.detail_colors.scroll_track,
+            StyleColor::ScrollThumb => style.detail_colors.scroll_thumb,
+            StyleColor::Overlay => style.detail_colors.overlay,
+        }
+    }
+
+    /// A background of this colour that follows style changes.
+    pub fn background(self, style: &UiStyle) -> impl Bundle {
+        (BackgroundColor(self.get(style)), StyledBackground(self))
+    }
+
+    /// A border of this colour that follows style changes.
+    pub fn border(self, style: &UiStyle) -> impl Bundle {
+        (BorderColor(self.get(style)), StyledBorder(self))
+    }
+
+    /// A text colour that follows style changes.
+    pub fn text(self, style: &UiStyle) -> impl Bundle {
+        (TextColor(self.get(style)), StyledText(self))
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct StyledBackground(pub StyleColor);
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct StyledBorder(pub StyleColor);
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct StyledText(pub StyleColor);
+
+/// The standard style, used where a theme file leaves out a section.
+impl Default for UiStyle {
+    fn default() -> Self {
+        UiStyle {
+            back_color: Color::Srgba(VIOLET_100),
+            label_color: Color::Srgba(DARK_SLATE_GRAY),
+            button_style: ButtonStyle::new(
+                BackgroundForeground::new(Color::Srgba(PINK_400), Color::Srgba(WHITE)),
+                BackgroundForeground::new(Color::Srgba(PINK_200), Color::Srgba(GRAY_700)),
+            ),
+            menu_item_style: ButtonStyle::new(
+                BackgroundForeground::new(Color::Srgba(BLUE_VIOLET), Color::Srgba(WHITE)),
+                BackgroundForeground::new(Color::Srgba(AQUAMARINE), Color::Srgba(BLACK)),
+            ),
+            severity_colors: SeverityColors {
+                info: BackgroundForeground::new(Color::Srgba(SKY_700), Color::Srgba(WHITE)),
+                success: BackgroundForeground::new(Color::Srgba(GREEN_700), Color::Srgba(WHITE)),
+                warning: BackgroundForeground::new(Color::Srgba(AMBER_400), Color::Srgba(GRAY_900)),
+                error: BackgroundForeground::new(Color::Srgba(RED_700), Color::Srgba(WHITE)),
+            },
+            detail_colors: DetailColors::default(),
+            spacing: Spacing::default(),
+            borders: Borders::default(),
+            radii: Radii::default(),
+            font_sizes: FontSizes::default(),
+        }
+    }
+}
@@ -0,0 +1,468 @@
+use std::marker::PhantomData;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use super::{
+    checkbox::{Checked, check_mark_box},
+    config::{StyleColor, StyleRole, UiStyle},
+    layouts::NodeModifier,
+    popup::{Popup, Popups, place_popup},
+    settings::{DropdownList, dropdown_menu_frame, styled_menu_item},
+    utils::Disabled,
+};
+
+/// Longer context menus scroll.
+const MAX_VISIBLE_ENTRIES: usize = 12;
+
+/// A choosable entry of a context menu, sending `data` with [`ContextMenuEvent`].
+#[derive(Clone, Debug)]
+pub struct ContextMenuItem<D> {
+    pub label: String,
+    pub data: D,
+    pub enabled: bool,
+    /// Checkable items show and toggle this when chosen.
+    pub checked: Option<bool>,
+}
+
+impl<D> ContextMenuItem<D> {
+    pub fn new(label: impl Into<String>, data: D) -> Self {
+        Self {
+            label: label.into(),
+            data,
+            enabled: true,
+            checked: None,
+        }
+    }
+
+    pub fn disabled(mut self) -> Self {
+        self.enabled = false;
+        self
+    }
+
+    pub fn checkable(mut self, checked: bool) -> Self {
+        self.checked = Some(checked);
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ContextMenuEntry<D> {
+    Item(ContextMenuItem<D>),
+    Separator,
+    Submenu {
+        label: String,
+        entries: Vec<ContextMenuEntry<D>>,
+    },
+}
+
+/// Entries of the menu opened by right-clicking the entity.
+/// The checked state of checkable items is kept here.
+#[derive(Component, Clone, Debug)]
+pub struct ContextMenu<D: Send + Sync + Clone + 'static>(pub Vec<ContextMenuEntry<D>>);
+
+impl<D: Send + Sync + Clone + 'static> ContextMenu<D> {
+    /// The entries of the submenu at `path`, the top level for an empty path.
+    fn entries(&self, path: &[usize]) -> Option<&[ContextMenuEntry<D>]> {
+        path.iter().try_fold(self.0.as_slice(), |entries, index| {
+            match entries.get(*index) {
+                Some(ContextMenuEntry::Submenu { entries, .. }) => Some(entries.as_slice()),
+                _ => None,
+            }
+        })
+    }
+
+    fn item_mut(&mut self, path: &[usize], index: usize) -> Option<&mut ContextMenuItem<D>> {
+        let mut entries = &mut self.0;
+        for i in path {
+            match entries.get_mut(*i) {
+                Some(ContextMenuEntry::Submenu { entries: e, .. }) => entries = e,
+                _ => return None,
+            }
+        }
+        match entries.get_mut(index) {
+            Some(ContextMenuEntry::Item(item)) => Some(item),
+            _ => None,
+        }
+    }
+}
+
+/// Sent when an enabled item of a context menu was chosen.
+#[derive(Event, Clone, Debug)]
+pub struct ContextMenuEvent<D: Send + Sync + Clone + 'static> {
+    /// The entity the menu was opened on.
+    pub target: Entity,
+    pub data: D,
+    /// The new state of a checkable item.
+    pub checked: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MenuAnchor {
+    /// The pointer location in logical window pixels.
+    Pointer(Vec2),
+    /// The submenu row a submenu opens next to.
+    Beside(Entity),
+}
+
+/// An open context menu or submenu of `target`'s [`ContextMenu`].
+#[derive(Component, Clone, Debug)]
+struct ContextMenuPopup {
+    target: Entity,
+    path: Vec<usize>,
+    anchor: MenuAnchor,
+}
+
+/// Index of a row within the entries of its menu.
+#[derive(Component, Clone, Copy, Debug, Deref)]
+struct ContextMenuRow(usize);
+
+fn entry_label<D>(entry: &ContextMenuEntry<D>) -> Option<String> {
+    match entry {
+        ContextMenuEntry::Item(item) => Some(item.label.clone()),
+        ContextMenuEntry::Submenu { label, .. } => Some(format!("{label}  >")),
+        ContextMenuEntry::Separator => None,
+    }
+}
+
+/// Spawns a hidden menu; it opens once it's been added to the world.
+fn spawn_menu<D: Send + Sync + Clone + 'static>(
+    commands: &mut Commands,
+    owner: Entity,
+    popup: ContextMenuPopup,
+    entries: &[ContextMenuEntry<D>],
+    ui_style: &UiStyle,
+) {
+    dropdown_menu_frame(
+        &mut *commands,
+        (popup, Popup { owner }, Name::new("ContextMenu")),
+        NodeModifier::new().force_absolute_pos().spawn_hidden(),
+        MAX_VISIBLE_ENTRIES,
+        ui_style,
+        |parent| {
+            for (index, entry) in entries.iter().enumerate() {
+                let Some(label) = entry_label(entry) else {
+                    parent.spawn((
+                        Node {
+                            height: Val::Px(ui_style.borders.normal),
+                            margin: UiRect::vertical(Val::Px(ui_style.spacing.small)),
+                            ..Default::default()
+                        },
+                        StyleColor::Border.background(ui_style),
+                        Pickable::IGNORE,
+                    ));
+                    continue;
+                };
+                let mut row = parent.spawn((
+                    styled_menu_item(label, StyleRole::MenuItem, ui_style),
+                    ContextMenuRow(index),
+                ));
+                if matches!(entry, ContextMenuEntry::Item(item) if !item.enabled) {
+                    row.insert(Disabled);
+                }
+                if let ContextMenuEntry::Item(ContextMenuItem {
+                    checked: Some(checked),
+                    ..
+                }) = entry
+                {
+                    let mark = row
+                        .commands()
+                        .spawn((
+                            Node {
+                                margin: UiRect::right(Val::Px(ui_style.spacing.medium)),
+                                ..Default::default()
+                            },
+                            children![check_mark_box(Checked::from(*checked), ui_style)],
+                        ))
+                        .id();
+                    row.insert_children(0, &[mark]);
+                }
+                row.observe(on_context_row_clicked::<D>)
+                    .observe(on_context_row_hovered::<D>);
+            }
+        },
+    );
+}
+
+/// The open menu `row` is part of.
+fn row_menu<'a>(
+    row: Entity,
+    parent_query: &Query<&ChildOf>,
+    menu_popups: &'a Query<(Entity, &ContextMenuPopup)>,
+) -> Option<(Entity, &'a ContextMenuPopup)> {
+    parent_query
+        .iter_ancestors(row)
+        .find_map(|e| menu_popups.get(e).ok())
+}
+
+/// Opens the submenu of `row`, spawning it unless it's already there.
+#[allow(clippy::too_many_arguments)]
+fn open_submenu<D: Send + Sync + Clone + 'static>(
+    commands: &mut Commands,
+    popups: &mut Popups,
+    row: Entity,
+    index: usize,
+    menu: &ContextMenuPopup,
+    entries: &[ContextMenuEntry<D>],
+    submenus: &Query<(Entity, &Popup), With<ContextMenuPopup>>,
+    ui_style: &UiStyle,
+) {
+    if let Some((submenu, _)) = submenus.iter().find(|(_, popup)| popup.owner == row) {
+        popups.open(submenu);
+        return;
+    }
+    let mut path = menu.path.clone();
+    path.push(index);
+    let popup = ContextMenuPopup {
+        target: menu.target,
+        path,
+        anchor: MenuAnchor::Beside(row),
+    };
+    spawn_menu(commands, row, popup, entries, ui_style);
+}
+
+/// Secondary clicks open the context menu of the closest entity with one.
+fn open_context_menu<D: Send + Sync + Clone + 'static>(
+    trigger: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    menus: Query<&ContextMenu<D>>,
+    popup_query: Query<(), With<Popup>>,
+    parent_query: Query<&ChildOf>,
+    ui_style: Res<UiStyle>,
+) {
+    let event = trigger.event();
+    if event.button != PointerButton::Secondary || trigger.target() != event.target {
+        return;
+    }
+    let Some(target) = std::iter::once(event.target)
+        .chain(parent_query.iter_ancestors(event.target))
+        .take_while(|e| !popup_query.contains(*e))
+        .find(|e| menus.contains(*e))
+    else {
+        return;
+    };
+    let menu = menus.get(target).expect("Checked above");
+    let popup = ContextMenuPopup {
+        target,
+        path: Vec::new(),
+        anchor: MenuAnchor::Pointer(event.pointer_location.position),
+    };
+    spawn_menu(&mut commands, target, popup, &menu.0, &ui_style);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn on_context_row_clicked<D: Send + Sync + Clone + 'static>(
+    mut trigger: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    rows: Query<&ContextMenuRow>,
+    parent_query: Query<&ChildOf>,
+    menu_popups: Query<(Entity, &ContextMenuPopup)>,
+    submenus: Query<(Entity, &Popup), With<ContextMenuPopup>>,
+    mut menus: Query<&mut ContextMenu<D>>,
+    mut popups: Popups,
+    mut events: EventWriter<ContextMenuEvent<D>>,
+    ui_style: Res<UiStyle>,
+) {
+    trigger.propagate(false);
+    let row_entity = trigger.target();
+    if trigger.event().button != PointerButton::Primary {
+        return;
+    }
+    let Ok(row) = rows.get(row_entity) else {
+        return;
+    };
+    let Some((_, menu_popup)) = row_menu(row_entity, &parent_query, &menu_popups) else {
+        return;
+    };
+    let Ok(mut menu) = menus.get_mut(menu_popup.target) else {
+        return;
+    };
+    match menu.entries(&menu_popup.path).and_then(|e| e.get(row.0)) {
+        Some(ContextMenuEntry::Submenu { entries, .. }) => {
+            let entries = entries.clone();
+            open_submenu(
+                &mut commands,
+                &mut popups,
+                row_entity,
+                row.0,
+                menu_popup,
+                &entries,
+                &submenus,
+                &ui_style,
+            );
+        }
+        Some(ContextMenuEntry::Item(item)) if item.enabled => {
+            let item = menu
+                .item_mut(&menu_popup.path, row.0)
+                .expect("Checked above");
+            if let Some(checked) = item.checked.as_mut() {
+                *checked = !*checked;
+            }
+            events.write(ContextMenuEvent {
+                target: menu_popup.target,
+                data: item.data.clone(),
+                checked: item.checked,
+            });
+            // Closing the top level menu closes its submenus as well.
+            let target = menu_popup.target;
+            if let Some((root, _)) = menu_popups
+                .iter()
+                .find(|(_, popup)| popup.target == target && popup.path.is_empty())
+            {
+                popups.close(root);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Hovering a submenu row opens its submenu, hovering anything else in a menu
+/// closes the submenus opened from it.
+#[allow(clippy::too_many_arguments)]
+fn on_context_row_hovered<D: Send + Sync + Clone + 'static>(
+    trigger: Trigger<Pointer<Over>>,
+    mut commands: Commands,
+    rows: Query<&ContextMenuRow>,
+    parent_query: Query<&ChildOf>,
+    menu_popups: Query<(Entity, &ContextMenuPopup)>,
+    submenus: Query<(Entity, &Popup), With<ContextMenuPopup>>,
+    menus: Query<&ContextMenu<D>>,
+    mut popups: Popups,
+    ui_style: Res<UiStyle>,
+) {
+    let row_entity = trigger.target();
+    let Ok(row) = rows.get(row_entity) else {
+        return;
+    };
+    let Some((menu_entity, menu_popup)) = row_menu(row_entity, &parent_query, &menu_popups) else {
+        return;
+    };
+    if !popups.is_open(menu_entity) {
+        return;
+    }
+    let entries = menus
+        .get(menu_popup.target)
+        .ok()
+        .and_then(|menu| menu.entries(&menu_popup.path))
+        .and_then(|entries| entries.get(row.0));
+    match entries {
+        Some(ContextMenuEntry::Submenu { entries, .. }) => open_submenu(
+            &mut commands,
+            &mut popups,
+            row_entity,
+            row.0,
+            menu_popup,
+            entries,
+            &submenus,
+            &ui_style,
+        ),
+        _ => popups.open(menu_entity),
+    }
+}
+
+/// Opens menus once they're spawned and despawns them once they're closed.
+fn open_spawned_menus(
+    mut commands: Commands,
+    menus: Query<(Entity, Ref<ContextMenuPopup>)>,
+    mut popups: Popups,
+) {
+    for (entity, menu) in menus {
+        if menu.is_added() {
+            popups.open(entity);
+        } else if !popups.is_open(entity) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Places menus at the pointer and submenus next to their row, inside the window.
+#[allow(clippy::type_complexity)]
+fn place_context_menus(
+    mut menus: Query<(
+        &ContextMenuPopup,
+        &mut Node,
+        &ComputedNode,
+        &ComputedNodeTarget,
+        &Children,
+    )>,
+    mut lists: Query<(&mut Node, &Children), (With<DropdownList>, Without<ContextMenuPopup>)>,
+    nodes: Query<(&ComputedNode, &GlobalTransform)>,
+    window: Single<&Window, With<PrimaryWindow>>,
+) {
+    for (menu, mut node, computed, target, children) in &mut menus {
+        if node.display == Display::None {
+            continue;
+        }
+        let mut list_iter = lists.iter_many_mut(children);
+        let Some((mut list_node, list_children)) = list_iter.fetch_next() else {
+            continue;
+        };
+        let visible_height: f32 = nodes
+            .iter_many(list_children)
+            .take(MAX_VISIBLE_ENTRIES)
+            .map(|(row, _)| row.size().y)
+            .sum();
+        let size = Vec2::new(computed.size().x, visible_height);
+        let screen = target.physical_size().as_vec2();
+        let (position, max_height) = match menu.anchor {
+            MenuAnchor::Pointer(pointer) => {
+                let pointer = pointer * window.scale_factor();
+                let anchor = Rect::from_corners(pointer, pointer);
+                let placement = place_popup(anchor, size, screen);
+                (anchor.min + placement.offset, placement.max_height)
+            }
+            MenuAnchor::Beside(row) => {
+                let Ok((row_node, row_transform)) = nodes.get(row) else {
+                    continue;
+                };
+                let row =
+                    Rect::from_center_size(row_transform.translation().truncate(), row_node.size());
+                let x = if row.max.x + size.x <= screen.x {
+                    row.max.x
+                } else {
+                    (row.min.x - size.x).max(0.0)
+                };
+                let y = row.min.y.min(screen.y - size.y).max(0.0);
+                (Vec2::new(x, y), (size.y > screen.y).then_some(screen.y))
+            }
+        };
+        let scale = computed.inverse_scale_factor();
+        let left = Val::Px(position.x * scale);
+        let top = Val::Px(position.y * scale);
+        if node.left != left || node.top != top {
+            node.left = left;
+            node.top = top;
+        }
+        let max_height = Val::Px(max_height.unwrap_or(visible_height) * scale);
+        if list_node.max_height != max_height {
+            list_node.max_height = max_height;
+        }
+    }
+}
+
+/// The systems shared by the context menus of all data types.
+struct ContextMenuLayoutPlugin;
+
+impl Plugin for ContextMenuLayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (open_spawned_menus, place_context_menus).chain());
+    }
+}
+
+/// Adds context menus sending [`ContextMenuEvent<D>`].
+pub struct ContextMenuPlugin<D>(PhantomData<D>);
+
+impl<D> Default for ContextMenuPlugin<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<D: Send + Sync + Clone + 'static> Plugin for ContextMenuPlugin<D> {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ContextMenuLayoutPlugin>() {
+            app.add_plugins(ContextMenuLayoutPlugin);
+        }
+        app.add_event::<ContextMenuEvent<D>>()
+            .add_observer(open_context_menu::<D>);
+    }
+}
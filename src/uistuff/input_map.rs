@@ -0,0 +1,605 @@
+use std::collections::BTreeMap;
+
+use crate::uistuff::utils::*;
+use bevy::{
+    ecs::{relationship::RelatedSpawnerCommands, system::SystemParam},
+    input::{
+        gamepad::{GamepadButtonChangedEvent, GamepadConnection, GamepadConnectionEvent},
+        keyboard::KeyboardInput,
+    },
+    input_focus::FocusedInput,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::UiStyle,
+    context_menu::{
+        ContextMenu, ContextMenuEntry, ContextMenuEvent, ContextMenuItem, ContextMenuPlugin,
+    },
+    layouts::{NodeModifier, button_box, vertically_centered},
+    modal::{ModalDialog, ModalResponse, spawn_modal},
+    staging::{Modified, RevertField, Staged, settings_row},
+    toast::{Severity, ShowToast},
+};
+
+/// How many bindings the settings page lets the player assign to each action.
+pub const BINDING_SLOTS: usize = 2;
+
+/// A single physical input that can trigger an action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl Binding {
+    pub fn label(&self) -> String {
+        match self {
+            Binding::Key(key) => {
+                let name = format!("{key:?}");
+                name.strip_prefix("Key")
+                    .or_else(|| name.strip_prefix("Digit"))
+                    .unwrap_or(&name)
+                    .to_string()
+            }
+            Binding::Mouse(button) => format!("Mouse {button:?}"),
+            Binding::Gamepad(button) => format!("Pad {button:?}"),
+        }
+    }
+}
+
+/// Actions known to the game with their default bindings, in the order
+/// they are listed on the settings page.
+pub const DEFAULT_BINDINGS: [(&str, &[Binding]); 6] = [
+    (
+        "Move up",
+        &[Binding::Key(KeyCode::KeyW), Binding::Key(KeyCode::ArrowUp)],
+    ),
+    (
+        "Move down",
+        &[
+            Binding::Key(KeyCode::KeyS),
+            Binding::Key(KeyCode::ArrowDown),
+        ],
+    ),
+    (
+        "Move left",
+        &[
+            Binding::Key(KeyCode::KeyA),
+            Binding::Key(KeyCode::ArrowLeft),
+        ],
+    ),
+    (
+        "Move right",
+        &[
+            Binding::Key(KeyCode::KeyD),
+            Binding::Key(KeyCode::ArrowRight),
+        ],
+    ),
+    (
+        "Interact",
+        &[
+            Binding::Key(KeyCode::KeyE),
+            Binding::Gamepad(GamepadButton::South),
+        ],
+    ),
+    (
+        "Pause",
+        &[
+            Binding::Key(KeyCode::Escape),
+            Binding::Gamepad(GamepadButton::Start),
+        ],
+    ),
+];
+
+/// Maps named actions to the binding in each of their slots.
+/// Game code should ask [`ActionInput`] about actions instead of reading raw keys.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputMap {
+    /// Cleared slots are kept as `None`, so the slots after them keep their index.
+    /// Trailing empty slots are left out.
+    pub actions: BTreeMap<String, Vec<Option<Binding>>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self {
+            actions: DEFAULT_BINDINGS
+                .iter()
+                .map(|(action, bindings)| (action.to_string(), default_slots(bindings)))
+                .collect(),
+        }
+    }
+}
+
+fn default_slots(bindings: &[Binding]) -> Vec<Option<Binding>> {
+    bindings.iter().copied().map(Some).collect()
+}
+
+impl InputMap {
+    pub fn slots(&self, action: &str) -> &[Option<Binding>] {
+        self.actions.get(action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// The bindings of the filled slots of `action`.
+    pub fn bindings(&self, action: &str) -> impl Iterator<Item = Binding> + '_ {
+        self.slots(action).iter().flatten().copied()
+    }
+
+    pub fn binding(&self, action: &str, slot: usize) -> Option<Binding> {
+        self.slots(action).get(slot).copied().flatten()
+    }
+
+    /// Finds the action and slot `binding` is currently assigned to.
+    pub fn find_binding(&self, binding: Binding) -> Option<(&str, usize)> {
+        self.actions.iter().find_map(|(action, bindings)| {
+            bindings
+                .iter()
+                .position(|b| *b == Some(binding))
+                .map(|slot| (action.as_str(), slot))
+        })
+    }
+
+    /// Assigns `binding` to a slot, or clears the slot when `binding` is `None`.
+    /// The other slots keep their bindings.
+    pub fn set_binding(&mut self, action: &str, slot: usize, binding: Option<Binding>) {
+        let slots = self.actions.entry(action.to_string()).or_default();
+        if slot >= slots.len() {
+            slots.resize(slot + 1, None);
+        }
+        slots[slot] = binding;
+        while slots.last() == Some(&None) {
+            slots.pop();
+        }
+    }
+
+    /// Assigns `binding` to a slot; if another slot already used it,
+    /// that slot receives the binding being replaced.
+    pub fn rebind(&mut self, action: &str, slot: usize, binding: Binding) {
+        let previous = self.binding(action, slot);
+        if let Some((other_action, other_slot)) =
+            self.find_binding(binding).map(|(a, s)| (a.to_string(), s))
+        {
+            self.set_binding(&other_action, other_slot, previous);
+        }
+        self.set_binding(action, slot, Some(binding));
+    }
+
+    pub fn reset_to_default(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Adds default bindings for actions missing from a loaded map,
+    /// so actions added after the settings were saved still work.
+    pub fn with_missing_defaults(mut self) -> Self {
+        for (action, bindings) in DEFAULT_BINDINGS {
+            self.actions
+                .entry(action.to_string())
+                .or_insert_with(|| default_slots(bindings));
+        }
+        self
+    }
+}
+
+/// Answers whether named actions were just triggered, based on the current [`InputMap`].
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+    map: Res<'w, InputMap>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    mouse: Res<'w, ButtonInput<MouseButton>>,
+    gamepads: Query<'w, 's, &'static Gamepad>,
+}
+
+impl ActionInput<'_, '_> {
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.map.bindings(action).any(|binding| match binding {
+            Binding::Key(key) => self.keys.just_pressed(key),
+            Binding::Mouse(button) => self.mouse.just_pressed(button),
+            Binding::Gamepad(button) => self.gamepads.iter().any(|g| g.just_pressed(button)),
+        })
+    }
+}
+
+/// A button on the settings page showing one binding slot of an action.
+#[derive(Component, Clone, Debug, PartialEq, Eq)]
+struct BindingButton {
+    action: String,
+    slot: usize,
+}
+
+/// Entries of the context menu of binding buttons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BindingMenuAction {
+    Clear,
+    ResetAction,
+}
+
+/// The menu of a binding button; clearing is disabled for empty slots.
+fn binding_context_menu(bound: bool) -> ContextMenu<BindingMenuAction> {
+    let clear = ContextMenuItem::new("Clear binding", BindingMenuAction::Clear);
+    ContextMenu(vec![
+        ContextMenuEntry::Item(if bound { clear } else { clear.disabled() }),
+        ContextMenuEntry::Separator,
+        ContextMenuEntry::Item(ContextMenuItem::new(
+            "Reset action to default",
+            BindingMenuAction::ResetAction,
+        )),
+    ])
+}
+
+/// The binding slot waiting for the next key or button press.
+#[derive(Resource, Default)]
+struct RebindCapture(Option<BindingButton>);
+
+struct Conflict {
+    target: BindingButton,
+    binding: Binding,
+}
+
+/// A captured binding that is already used by another slot, waiting for the player's decision.
+#[derive(Resource, Default)]
+struct PendingConflict(Option<Conflict>);
+
+#[derive(Component)]
+struct ConflictDialog;
+
+/// Marks the entries of one action's row on the settings page.
+#[derive(Component, Clone, Debug, PartialEq, Eq)]
+struct ActionRow(String);
+
+/// Spawns one settings row per action followed by a reset button, starting at grid row `first_row`.
+pub fn spawn_controls_settings(
+    parent: &mut RelatedSpawnerCommands<ChildOf>,
+    ui_assets: &UIAssets,
+    ui_style: &UiStyle,
+    input_map: &InputMap,
+    first_row: i16,
+) {
+    let font = ui_assets
+        .font
+        .clone()
+        .expect("Font should be loaded already");
+    let mut row = first_row;
+    for (action, _) in DEFAULT_BINDINGS {
+        let modifier = settings_row(
+            parent,
+            action,
+            ActionRow(action.to_string()),
+            font.clone(),
+            row,
+            ui_style,
+        );
+        vertically_centered(&mut *parent, (), modifier, |parent| {
+            for slot in 0..BINDING_SLOTS {
+                parent
+                    .spawn((
+                        button_box(
+                            binding_label(input_map.binding(action, slot)),
+                            font.clone(),
+                            NodeModifier::new(),
+                            ui_style,
+                        ),
+                        BindingButton {
+                            action: action.to_string(),
+                            slot,
+                        },
+                        binding_context_menu(input_map.binding(action, slot).is_some()),
+                        Focusable,
+                    ))
+                    .observe(on_binding_button_clicked)
+                    .observe(swallow_key_while_capturing)
+                    .observe(swallow_gamepad_button_while_capturing);
+            }
+        });
+        row += 1;
+    }
+    parent
+        .spawn(button_box(
+            "Reset controls",
+            font,
+            NodeModifier::new()
+                .set_grid_column(GridPlacement::start_span(3, 1))
+                .set_grid_row(GridPlacement::start_span(row, 1)),
+            ui_style,
+        ))
+        .observe(on_reset_controls_clicked);
+}
+
+fn binding_label(binding: Option<Binding>) -> String {
+    binding.map_or_else(|| "-".to_string(), |b| b.label())
+}
+
+fn on_binding_button_clicked(
+    mut trigger: Trigger<Pointer<Click>>,
+    buttons: Query<&BindingButton>,
+    mut capture: ResMut<RebindCapture>,
+    conflict: Res<PendingConflict>,
+) {
+    if trigger.event().button != PointerButton::Primary {
+        return;
+    }
+    trigger.propagate(false);
+    if conflict.0.is_some() {
+        return;
+    }
+    if let Ok(button) = buttons.get(trigger.target()) {
+        capture.0 = Some(button.clone());
+    }
+}
+
+/// The pressed binding button keeps the focus while capturing, so the captured
+/// input doesn't also reach widgets and shortcuts further up.
+fn swallow_key_while_capturing(
+    mut trigger: Trigger<FocusedInput<KeyboardInput>>,
+    capture: Res<RebindCapture>,
+) {
+    if capture.0.is_some() {
+        trigger.propagate(false);
+    }
+}
+
+fn swallow_gamepad_button_while_capturing(
+    mut trigger: Trigger<FocusedInput<GamepadButtonChangedEvent>>,
+    capture: Res<RebindCapture>,
+) {
+    if capture.0.is_some() {
+        trigger.propagate(false);
+    }
+}
+
+/// Escape cancels the capture, so it can't be assigned from the settings page.
+#[allow(clippy::too_many_arguments)]
+fn capture_binding(
+    mut commands: Commands,
+    mut capture: ResMut<RebindCapture>,
+    mut conflict: ResMut<PendingConflict>,
+    mut input_map: ResMut<Staged<InputMap>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+    ui_assets: Res<UIAssets>,
+    ui_style: Res<UiStyle>,
+) {
+    if capture.0.is_none() {
+        return;
+    }
+    let pressed = keys
+        .get_just_pressed()
+        .next()
+        .map(|k| Binding::Key(*k))
+        .or_else(|| mouse.get_just_pressed().next().map(|b| Binding::Mouse(*b)))
+        .or_else(|| {
+            gamepads
+                .iter()
+                .find_map(|g| g.get_just_pressed().next())
+                .map(|b| Binding::Gamepad(*b))
+        });
+    let Some(binding) = pressed else {
+        return;
+    };
+    let target = capture.0.take().expect("Checked above");
+    if binding == Binding::Key(KeyCode::Escape) {
+        return;
+    }
+    match input_map.find_binding(binding) {
+        Some((action, slot)) if action != target.action || slot != target.slot => {
+            let message = format!(
+                "{} is already bound to {action}.\nSwap the bindings?",
+                binding.label()
+            );
+            spawn_conflict_dialog(&mut commands, &ui_assets, &ui_style, message);
+            conflict.0 = Some(Conflict { target, binding });
+        }
+        Some(_) => (),
+        None => input_map.rebind(&target.action, target.slot, binding),
+    }
+}
+
+fn spawn_conflict_dialog(
+    commands: &mut Commands,
+    ui_assets: &UIAssets,
+    ui_style: &UiStyle,
+    message: String,
+) {
+    let dialog = ModalDialog::new("Binding conflict", message)
+        .with_buttons(["Swap", "Cancel"])
+        .with_escape_button(1);
+    let entity = spawn_modal(
+        commands,
+        (ConflictDialog, Name::new("BindingConflict")),
+        dialog,
+        ui_assets,
+        ui_style,
+    );
+    commands.entity(entity).observe(on_conflict_response);
+}
+
+fn on_conflict_response(
+    trigger: Trigger<ModalResponse>,
+    mut conflict: ResMut<PendingConflict>,
+    mut input_map: ResMut<Staged<InputMap>>,
+) {
+    let Some(Conflict { target, binding }) = conflict.0.take() else {
+        return;
+    };
+    // Swap is the first button, Cancel the second.
+    if trigger.event().button == 0 {
+        input_map.rebind(&target.action, target.slot, binding);
+    }
+}
+
+/// Stages the default bindings; they take effect once the page is applied.
+fn on_reset_controls_clicked(
+    mut trigger: Trigger<Pointer<Click>>,
+    mut input_map: ResMut<Staged<InputMap>>,
+) {
+    trigger.propagate(false);
+    input_map.reset_to_default();
+}
+
+/// Stages the change chosen in a binding button's context menu.
+fn on_binding_menu_event(
+    mut events: EventReader<ContextMenuEvent<BindingMenuAction>>,
+    buttons: Query<&BindingButton>,
+    mut input_map: ResMut<Staged<InputMap>>,
+) {
+    for event in events.read() {
+        let Ok(button) = buttons.get(event.target) else {
+            continue;
+        };
+        match event.data {
+            BindingMenuAction::Clear => input_map.set_binding(&button.action, button.slot, None),
+            BindingMenuAction::ResetAction => {
+                let defaults = InputMap::default().slots(&button.action).to_vec();
+                input_map.actions.insert(button.action.clone(), defaults);
+            }
+        }
+    }
+}
+
+fn on_action_row_reverted(
+    mut events: EventReader<RevertField>,
+    rows: Query<&ActionRow>,
+    input_map: Res<InputMap>,
+    mut staged: ResMut<Staged<InputMap>>,
+) {
+    for event in events.read() {
+        let Ok(ActionRow(action)) = rows.get(event.entity) else {
+            continue;
+        };
+        staged
+            .actions
+            .insert(action.clone(), input_map.slots(action).to_vec());
+    }
+}
+
+fn update_action_rows_modified(
+    input_map: Res<InputMap>,
+    staged: Res<Staged<InputMap>>,
+    rows: Query<(&ActionRow, &mut Modified)>,
+) {
+    for (ActionRow(action), mut modified) in rows {
+        let is_modified = input_map.slots(action) != staged.slots(action);
+        modified.set_if_neq(Modified(is_modified));
+    }
+}
+
+fn sync_binding_buttons(
+    input_map: Res<Staged<InputMap>>,
+    capture: Res<RebindCapture>,
+    buttons: Query<(
+        &BindingButton,
+        &Children,
+        &mut ContextMenu<BindingMenuAction>,
+    )>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (button, children, menu) in buttons {
+        let bound = input_map.binding(&button.action, button.slot).is_some();
+        // Only the Clear entry depends on the binding, the rest of the menu stays untouched.
+        if let Some(mut enabled) = menu.filter_map_unchanged(|menu| {
+            menu.0.iter_mut().find_map(|entry| match entry {
+                ContextMenuEntry::Item(item) if item.data == BindingMenuAction::Clear => {
+                    Some(&mut item.enabled)
+                }
+                _ => None,
+            })
+        }) {
+            enabled.set_if_neq(bound);
+        }
+        let label = if capture.0.as_ref() == Some(button) {
+            "Press a key...".to_string()
+        } else {
+            binding_label(input_map.binding(&button.action, button.slot))
+        };
+        set_box_text(children, &mut text_query, label);
+    }
+}
+
+fn notify_gamepad_connections(
+    mut events: EventReader<GamepadConnectionEvent>,
+    mut toasts: EventWriter<ShowToast>,
+) {
+    for event in events.read() {
+        let toast = match &event.connection {
+            GamepadConnection::Connected { name, .. } => {
+                ShowToast::new(Severity::Info, format!("{name} connected"))
+            }
+            GamepadConnection::Disconnected => {
+                ShowToast::new(Severity::Warning, "Controller disconnected")
+            }
+        };
+        toasts.write(toast);
+    }
+}
+
+pub struct InputMapPlugin;
+
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RebindCapture>()
+            .init_resource::<PendingConflict>()
+            .add_plugins(ContextMenuPlugin::<BindingMenuAction>::default())
+            .add_systems(
+                Update,
+                (
+                    capture_binding,
+                    on_binding_menu_event.run_if(on_event::<ContextMenuEvent<BindingMenuAction>>),
+                    on_action_row_reverted.run_if(on_event::<RevertField>),
+                    sync_binding_buttons.run_if(
+                        resource_changed::<Staged<InputMap>>.or(resource_changed::<RebindCapture>),
+                    ),
+                    update_action_rows_modified.run_if(
+                        resource_changed::<InputMap>.or(resource_changed::<Staged<InputMap>>),
+                    ),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                notify_gamepad_connections.run_if(on_event::<GamepadConnectionEvent>),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: Binding = Binding::Key(KeyCode::KeyW);
+    const UP: Binding = Binding::Key(KeyCode::ArrowUp);
+    const E: Binding = Binding::Key(KeyCode::KeyE);
+
+    #[test]
+    fn clearing_a_slot_keeps_later_slots() {
+        let mut map = InputMap::default();
+        map.set_binding("Move up", 0, None);
+        assert_eq!(map.binding("Move up", 0), None);
+        assert_eq!(map.binding("Move up", 1), Some(UP));
+        assert_eq!(map.bindings("Move up").collect::<Vec<_>>(), [UP]);
+        map.set_binding("Move up", 0, Some(W));
+        assert_eq!(map, InputMap::default());
+    }
+
+    #[test]
+    fn clearing_the_last_slot_trims_it() {
+        let mut map = InputMap::default();
+        map.set_binding("Move up", 1, None);
+        assert_eq!(map.slots("Move up"), [Some(W)]);
+        map.set_binding("Move up", 3, None);
+        assert_eq!(map.slots("Move up"), [Some(W)]);
+        map.set_binding("Move up", 2, Some(UP));
+        assert_eq!(map.slots("Move up"), [Some(W), None, Some(UP)]);
+    }
+
+    #[test]
+    fn rebind_swaps_with_the_other_slot() {
+        let mut map = InputMap::default();
+        map.rebind("Move up", 0, E);
+        assert_eq!(map.binding("Move up", 0), Some(E));
+        assert_eq!(map.binding("Interact", 0), Some(W));
+        assert_eq!(map.find_binding(W), Some(("Interact", 0)));
+    }
+}
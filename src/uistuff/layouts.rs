@@ -61,25 +61,33 @@ impl NodeModifier {
     }
 }
 
+/// A text box painted with the normal colours of `role`, repainted whenever
+/// the [`UiStyle`] or its [`VisualState`] changes. The text has the font size
+/// of the [`TextRole`] of `role`.
 pub fn text_box(
     text: impl Into<String>,
     font: Handle<Font>,
     node_modifier: NodeModifier,
-    style: BackgroundForeground,
+    role: StyleRole,
+    style: &UiStyle,
 ) -> impl Bundle {
+    let colors = role.colors(style).normal_colors;
     (
         node_modifier.modify(Node {
-            border: UiRect::all(Val::Px(2.0)),
-            padding: UiRect::axes(Val::Auto, Val::Px(5.0)),
+            border: UiRect::all(Val::Px(style.borders.normal)),
+            padding: UiRect::axes(Val::Auto, Val::Px(style.spacing.small)),
             ..Default::default()
         }),
-        BackgroundColor(style.back_color),
+        role,
+        BackgroundColor(colors.back_color),
+        BorderColor(colors.border_color),
+        BorderRadius::all(Val::Px(style.radii.small)),
         children![(
             Text::new(text),
-            TextColor(style.fore_color),
+            TextColor(colors.fore_color),
             TextFont {
                 font,
-                font_size: 32.0,
+                font_size: role.font_size(style),
                 ..Default::default()
             }
         )],
@@ -90,10 +98,10 @@ pub fn button_box(
     text: impl Into<String>,
     font: Handle<Font>,
     node_modifier: NodeModifier,
-    style: ButtonStyle,
+    style: &UiStyle,
 ) -> impl Bundle {
-    let result = text_box(text, font, node_modifier, style.normal_colors);
-    (result, ChangeColorOnHover::from(style), Button)
+    let result = text_box(text, font, node_modifier, StyleRole::Button, style);
+    (result, VisualState::Normal, Button)
 }
 
 pub fn vertically_centered(
@@ -117,6 +125,26 @@ pub fn vertically_centered(
         .id()
 }
 
+pub fn vertical_stack(
+    mut command: impl GenericSpawner,
+    extra_components: impl Bundle,
+    node_modifier: NodeModifier,
+    func: impl FnOnce(&mut RelatedSpawnerCommands<ChildOf>),
+) -> Entity {
+    command
+        .generic_spawn((
+            extra_components,
+            node_modifier.modify(Node {
+                display: Display::Flex,
+                align_items: AlignItems::Start,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            }),
+        ))
+        .with_children(func)
+        .id()
+}
+
 pub fn grid_hor_center_layout(
     mut command: impl GenericSpawner,
     extra_components: impl Bundle,
@@ -142,3 +170,37 @@ pub fn grid_hor_center_layout(
         .with_children(child_spawner)
         .id()
 }
+
+/// Spawns a dimmed full-screen overlay with a centered, framed column panel for
+/// the dialog contents. Pass a [`GlobalZIndex`] in `extra_components` to lift it
+/// above the rest of the UI; modals get theirs from the modal stack.
+pub fn dialog_overlay(
+    command: impl GenericSpawner,
+    extra_components: impl Bundle,
+    style: &UiStyle,
+    func: impl FnOnce(&mut RelatedSpawnerCommands<ChildOf>),
+) -> Entity {
+    vertically_centered(
+        command,
+        (extra_components, StyleColor::Overlay.background(style)),
+        NodeModifier::root().force_absolute_pos(),
+        |parent| {
+            parent
+                .spawn((
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Stretch,
+                        margin: UiRect::horizontal(Val::Auto),
+                        padding: UiRect::all(Val::Px(style.spacing.medium)),
+                        row_gap: Val::Px(style.spacing.medium),
+                        border: UiRect::all(Val::Px(style.borders.thin)),
+                        ..Default::default()
+                    },
+                    StyleColor::Background.background(style),
+                    StyleColor::Border.border(style),
+                    BorderRadius::all(Val::Px(style.radii.medium)),
+                ))
+                .with_children(func);
+        },
+    )
+}
@@ -152,7 +152,7 @@ mod internal {
                             modifier
                                 .clone()
                                 .set_grid_row(GridPlacement::start_span(idx, 1)),
-                            ui_style.button_style,
+                            &ui_style,
                         ))
                         .observe(mouseclick_observer(item.action.clone()));
                 }
@@ -1,5 +1,24 @@
+pub mod audio;
+pub mod checkbox;
 pub mod config;
+pub mod context_menu;
+pub mod input_map;
 pub mod layouts;
 pub mod main_menu;
+pub mod modal;
+pub mod multi_select;
+pub mod persistence;
+pub mod popup;
+pub mod radio;
+pub mod searchable_combobox;
 pub mod settings;
+pub mod slider;
+pub mod spin_box;
+pub mod staging;
+pub mod tabs;
+pub mod text_input;
+pub mod theme;
+pub mod toast;
+pub mod tooltip;
 pub mod utils;
+pub mod video;
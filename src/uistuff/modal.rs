@@ -0,0 +1,371 @@
+use crate::uistuff::utils::*;
+use bevy::{
+    input::{ButtonState, gamepad::GamepadButtonChangedEvent, keyboard::KeyboardInput},
+    input_focus::{FocusedInput, InputFocus},
+    prelude::*,
+};
+
+use super::{
+    config::{StyleRole, UiStyle},
+    layouts::{NodeModifier, button_box, dialog_overlay, text_box, vertically_centered},
+};
+
+/// Z-index of the lowest open modal, above popups. Modals opened on top of it
+/// are [`MODAL_Z_STEP`] higher each, leaving room for the popups of the modal below.
+pub const MODAL_Z_INDEX: i32 = 200;
+pub const MODAL_Z_STEP: i32 = 10;
+
+/// What a modal dialog shows. Spawn it with [`spawn_modal`].
+#[derive(Clone, Debug, Default)]
+pub struct ModalDialog {
+    pub title: String,
+    pub body: String,
+    pub buttons: Vec<String>,
+    /// The button Escape (or the gamepad's east button) stands for.
+    /// Without one the dialog can only be closed through its buttons.
+    pub escape_button: Option<usize>,
+}
+
+impl ModalDialog {
+    pub fn new(title: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            body: body.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_buttons(mut self, buttons: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.buttons = buttons.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_escape_button(mut self, index: usize) -> Self {
+        self.escape_button = Some(index);
+        self
+    }
+}
+
+/// The root of an open modal dialog. Everything below it is blocked
+/// and the focus stays on its buttons until it's closed.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Modal {
+    pub escape_button: Option<usize>,
+}
+
+/// The body text of a modal, for dialogs that update it while open.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ModalBody;
+
+#[derive(Component, Clone, Copy, Debug, Deref)]
+struct ModalButton(usize);
+
+/// Sent when a button of a modal was chosen, right before the modal is despawned.
+/// It's also triggered on the modal entity, so it can be observed there.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ModalResponse {
+    pub dialog: Entity,
+    pub button: usize,
+}
+
+/// Open modals from the bottom to the topmost one,
+/// each with the entity that had the focus before it opened.
+#[derive(Resource, Default, Debug)]
+pub struct ModalStack(Vec<(Entity, Option<Entity>)>);
+
+impl ModalStack {
+    pub fn top(&self) -> Option<Entity> {
+        self.0.last().map(|(modal, _)| *modal)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Run condition for input handling that shouldn't happen below a modal.
+pub fn no_modal_open(stack: Res<ModalStack>) -> bool {
+    stack.is_empty()
+}
+
+/// Spawns a modal dialog above everything else. It isn't tied to any state,
+/// so it stays open until one of its buttons is chosen or it's despawned.
+/// Observe [`ModalResponse`] on the returned entity to learn which button was chosen.
+pub fn spawn_modal(
+    commands: &mut Commands,
+    extra_components: impl Bundle,
+    dialog: ModalDialog,
+    ui_assets: &UIAssets,
+    ui_style: &UiStyle,
+) -> Entity {
+    let font = ui_assets
+        .font
+        .clone()
+        .expect("Font should be loaded already");
+    let modal = Modal {
+        escape_button: dialog.escape_button,
+    };
+    let entity = dialog_overlay(
+        &mut *commands,
+        (extra_components, modal, Name::new("Modal")),
+        ui_style,
+        |parent| {
+            parent.spawn(text_box(
+                dialog.title,
+                font.clone(),
+                NodeModifier::new(),
+                StyleRole::Title,
+                ui_style,
+            ));
+            parent.spawn((
+                text_box(
+                    dialog.body,
+                    font.clone(),
+                    NodeModifier::new(),
+                    StyleRole::Highlight,
+                    ui_style,
+                ),
+                ModalBody,
+            ));
+            vertically_centered(parent, (), NodeModifier::new(), |parent| {
+                for (index, label) in dialog.buttons.iter().enumerate() {
+                    parent
+                        .spawn((
+                            button_box(label, font.clone(), NodeModifier::new(), ui_style),
+                            ModalButton(index),
+                            Focusable,
+                        ))
+                        .observe(on_modal_button_clicked);
+                }
+            });
+        },
+    );
+    commands
+        .entity(entity)
+        .observe(on_modal_key)
+        .observe(on_modal_gamepad_button);
+    entity
+}
+
+fn respond(
+    commands: &mut Commands,
+    dialog: Entity,
+    button: usize,
+    responses: &mut EventWriter<ModalResponse>,
+) {
+    let response = ModalResponse { dialog, button };
+    info!("Modal {dialog} closed with button {button}");
+    responses.write(response);
+    commands.trigger_targets(response, dialog);
+    commands.entity(dialog).try_despawn();
+}
+
+/// The buttons of `modal` in order, with their entities.
+fn modal_buttons(
+    modal: Entity,
+    children_query: &Query<&Children>,
+    buttons: &Query<&ModalButton>,
+) -> Vec<(usize, Entity)> {
+    let mut result: Vec<_> = children_query
+        .iter_descendants(modal)
+        .filter_map(|e| buttons.get(e).ok().map(|b| (b.0, e)))
+        .collect();
+    result.sort();
+    result
+}
+
+/// Focuses the button `offset` places away from the focused one, wrapping around.
+fn move_modal_focus(
+    modal: Entity,
+    offset: isize,
+    focus: &mut InputFocus,
+    children_query: &Query<&Children>,
+    buttons: &Query<&ModalButton>,
+) {
+    let modal_buttons = modal_buttons(modal, children_query, buttons);
+    if modal_buttons.is_empty() {
+        return;
+    }
+    let count = modal_buttons.len() as isize;
+    let current = modal_buttons
+        .iter()
+        .position(|(_, e)| focus.0 == Some(*e))
+        .map_or(-1, |i| i as isize);
+    let next = if current < 0 && offset < 0 {
+        count - 1
+    } else {
+        (current + offset).rem_euclid(count)
+    };
+    focus.0 = Some(modal_buttons[next as usize].1);
+}
+
+fn on_modal_button_clicked(
+    mut trigger: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    buttons: Query<&ModalButton>,
+    parent_query: Query<&ChildOf>,
+    modals: Query<(), With<Modal>>,
+    mut responses: EventWriter<ModalResponse>,
+) {
+    let Ok(button) = buttons.get(trigger.target()) else {
+        return;
+    };
+    trigger.propagate(false);
+    if let Some(dialog) = parent_query
+        .iter_ancestors(trigger.target())
+        .find(|e| modals.contains(*e))
+    {
+        respond(&mut commands, dialog, button.0, &mut responses);
+    }
+}
+
+/// Keys never leave a modal, so shortcuts of the UI below it stay inactive.
+/// Escape is also taken out of [`ButtonInput`], so systems polling it later
+/// in the frame don't see the press that closed the modal.
+#[allow(clippy::too_many_arguments)]
+fn on_modal_key(
+    mut trigger: Trigger<FocusedInput<KeyboardInput>>,
+    mut commands: Commands,
+    modals: Query<&Modal>,
+    children_query: Query<&Children>,
+    buttons: Query<&ModalButton>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut focus: ResMut<InputFocus>,
+    mut responses: EventWriter<ModalResponse>,
+) {
+    trigger.propagate(false);
+    let dialog = trigger.target();
+    let input = &trigger.event().input;
+    if input.state != ButtonState::Pressed {
+        return;
+    }
+    let Ok(modal) = modals.get(dialog) else {
+        return;
+    };
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let offset = match input.key_code {
+        KeyCode::Escape => {
+            keys.clear_just_pressed(KeyCode::Escape);
+            if let Some(button) = modal.escape_button {
+                respond(&mut commands, dialog, button, &mut responses);
+            }
+            return;
+        }
+        KeyCode::Enter | KeyCode::NumpadEnter | KeyCode::Space => {
+            if let Some(button) = focus.0.and_then(|e| buttons.get(e).ok()) {
+                respond(&mut commands, dialog, button.0, &mut responses);
+            }
+            return;
+        }
+        KeyCode::Tab if shift => -1,
+        KeyCode::ArrowLeft | KeyCode::ArrowUp => -1,
+        KeyCode::Tab | KeyCode::ArrowRight | KeyCode::ArrowDown => 1,
+        _ => return,
+    };
+    move_modal_focus(dialog, offset, &mut focus, &children_query, &buttons);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn on_modal_gamepad_button(
+    mut trigger: Trigger<FocusedInput<GamepadButtonChangedEvent>>,
+    mut commands: Commands,
+    modals: Query<&Modal>,
+    children_query: Query<&Children>,
+    buttons: Query<&ModalButton>,
+    mut focus: ResMut<InputFocus>,
+    mut responses: EventWriter<ModalResponse>,
+) {
+    trigger.propagate(false);
+    let dialog = trigger.target();
+    let input = &trigger.event().input;
+    if input.state != ButtonState::Pressed {
+        return;
+    }
+    let Ok(modal) = modals.get(dialog) else {
+        return;
+    };
+    let offset = match input.button {
+        GamepadButton::East => {
+            if let Some(button) = modal.escape_button {
+                respond(&mut commands, dialog, button, &mut responses);
+            }
+            return;
+        }
+        GamepadButton::South => {
+            if let Some(button) = focus.0.and_then(|e| buttons.get(e).ok()) {
+                respond(&mut commands, dialog, button.0, &mut responses);
+            }
+            return;
+        }
+        GamepadButton::DPadLeft | GamepadButton::DPadUp => -1,
+        GamepadButton::DPadRight | GamepadButton::DPadDown => 1,
+        _ => return,
+    };
+    move_modal_focus(dialog, offset, &mut focus, &children_query, &buttons);
+}
+
+fn on_modal_added(
+    trigger: Trigger<OnAdd, Modal>,
+    mut commands: Commands,
+    mut stack: ResMut<ModalStack>,
+    focus: Res<InputFocus>,
+) {
+    let modal = trigger.target();
+    stack.0.push((modal, focus.0));
+    commands.entity(modal).insert(GlobalZIndex(
+        MODAL_Z_INDEX + (stack.0.len() as i32 - 1) * MODAL_Z_STEP,
+    ));
+}
+
+/// Gives the focus back to where it was before the topmost modal opened.
+fn on_modal_removed(
+    trigger: Trigger<OnRemove, Modal>,
+    mut stack: ResMut<ModalStack>,
+    entities: Query<()>,
+    mut focus: ResMut<InputFocus>,
+) {
+    let modal = trigger.target();
+    let Some(index) = stack.0.iter().position(|(e, _)| *e == modal) else {
+        return;
+    };
+    let (_, previous_focus) = stack.0.remove(index);
+    if index == stack.0.len() {
+        focus.0 = previous_focus.filter(|e| entities.contains(*e));
+    }
+}
+
+/// Keeps the focus inside the topmost modal, on its first button by default.
+fn trap_modal_focus(
+    stack: Res<ModalStack>,
+    mut focus: ResMut<InputFocus>,
+    parent_query: Query<&ChildOf>,
+    children_query: Query<&Children>,
+    buttons: Query<&ModalButton>,
+) {
+    let Some(top) = stack.top() else {
+        return;
+    };
+    let inside = focus.0.is_some_and(|focused| {
+        std::iter::once(focused)
+            .chain(parent_query.iter_ancestors(focused))
+            .any(|e| e == top)
+    });
+    if !inside {
+        let first = modal_buttons(top, &children_query, &buttons)
+            .first()
+            .map(|(_, e)| *e);
+        focus.0 = Some(first.unwrap_or(top));
+    }
+}
+
+pub struct ModalPlugin;
+
+impl Plugin for ModalPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ModalStack>()
+            .add_event::<ModalResponse>()
+            .add_observer(on_modal_added)
+            .add_observer(on_modal_removed)
+            .add_systems(Update, trap_modal_focus);
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::BTreeSet;
+
+use crate::uistuff::utils::*;
+use bevy::{
+    input::{ButtonState, keyboard::KeyboardInput},
+    input_focus::{FocusedInput, InputFocus},
+    prelude::*,
+};
+
+use super::{
+    checkbox::{CheckboxChanged, CheckboxGroup, Checked, checkbox_box},
+    config::{StyleRole, UiStyle, VisualState},
+    layouts::{NodeModifier, text_box},
+    popup::{Popup, Popups},
+    settings::{
+        ComboboxItems, DropdownItemIndex, DropdownMenu, DropdownMenuButton, DropdownMenuMenu,
+        HighlightedItem, PreviousLayout, dropdown_menu_frame, on_combobutton_clicked,
+        on_dropdown_item_hovered,
+    },
+};
+
+/// Indices of the checked items of a multi-select dropdown.
+/// Changing it from code updates the summary and the check marks.
+#[derive(Component, Clone, Debug, Default, Deref, DerefMut, PartialEq, Eq)]
+pub struct SelectedItems(pub BTreeSet<usize>);
+
+/// Sent when the user checks or unchecks an item of a multi-select dropdown.
+#[derive(Event, Clone, Debug)]
+pub struct MultiSelectChanged {
+    pub dropdown: Entity,
+    pub index: usize,
+    pub checked: bool,
+    /// All checked items after the change.
+    pub selected: BTreeSet<usize>,
+}
+
+/// A dropdown with checkable items. Its popup stays open while toggling them
+/// and the button shows a summary of the checked ones. An "All" item above
+/// the others checks or unchecks all of them.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct MultiSelect;
+
+/// "None", the only checked label, "All" or the number of checked items.
+fn multi_select_summary(selected: &SelectedItems, items: &ComboboxItems) -> String {
+    match selected.len() {
+        0 => "None".to_string(),
+        1 => selected
+            .iter()
+            .next()
+            .and_then(|i| items.get(*i))
+            .map_or_else(String::new, |item| item.label.clone()),
+        n if n == items.len() => "All".to_string(),
+        n => format!("{n} selected"),
+    }
+}
+
+/// A checkbox painted like a menu item.
+fn menu_checkbox(
+    label: &str,
+    checked: Checked,
+    font: Handle<Font>,
+    ui_style: &UiStyle,
+) -> impl Bundle {
+    let role = StyleRole::MenuItem;
+    (
+        checkbox_box(label, font, NodeModifier::new(), checked, ui_style),
+        role,
+        VisualState::Normal,
+        BackgroundColor(role.colors(ui_style).normal_colors.back_color),
+    )
+}
+
+/// A dropdown item.
+fn multi_select_item(
+    label: &str,
+    index: usize,
+    checked: bool,
+    font: Handle<Font>,
+    ui_style: &UiStyle,
+) -> impl Bundle {
+    (
+        menu_checkbox(label, Checked::from(checked), font, ui_style),
+        DropdownItemIndex(index),
+    )
+}
+
+/// Spawns a dropdown whose items can be checked independently.
+/// `selected` are the indices of the items checked at first.
+pub fn spawn_multi_select(
+    mut commands: impl GenericSpawner,
+    extra_components: impl Bundle,
+    menu: DropdownMenu,
+    selected: impl IntoIterator<Item = usize>,
+    node_modifier: NodeModifier,
+    ui_assets: &UIAssets,
+    ui_style: &UiStyle,
+) -> Entity {
+    let font = ui_assets
+        .font
+        .clone()
+        .expect("Font should be loaded already");
+    let selected = SelectedItems(
+        selected
+            .into_iter()
+            .filter(|i| *i < menu.items.len())
+            .collect(),
+    );
+    let items = ComboboxItems(menu.items.clone());
+    let summary = multi_select_summary(&selected, &items);
+    let checked: Vec<bool> = (0..menu.items.len())
+        .map(|i| selected.contains(&i))
+        .collect();
+    commands
+        .generic_spawn((
+            extra_components,
+            text_box(
+                summary,
+                font.clone(),
+                node_modifier,
+                StyleRole::Normal,
+                ui_style,
+            ),
+            DropdownMenuButton,
+            MultiSelect,
+            selected,
+            items,
+            Name::new("MultiSelect"),
+            PreviousLayout::default(),
+        ))
+        .with_children(|parent| {
+            let owner = parent.target_entity();
+            let dropdown = dropdown_menu_frame(
+                &mut *parent,
+                (DropdownMenuMenu, Name::new("Dropdown"), Popup { owner }),
+                NodeModifier::new().force_absolute_pos().spawn_hidden(),
+                menu.max_visible_items,
+                ui_style,
+                |parent| {
+                    // Its state follows the items, see `CheckboxGroup`.
+                    let all = parent
+                        .spawn(menu_checkbox("All", Checked::Off, font.clone(), ui_style))
+                        .id();
+                    let members = menu
+                        .items
+                        .iter()
+                        .enumerate()
+                        .map(|(index, item)| {
+                            parent
+                                .spawn(multi_select_item(
+                                    &item.label,
+                                    index,
+                                    checked[index],
+                                    font.clone(),
+                                    ui_style,
+                                ))
+                                .id()
+                        })
+                        .collect();
+                    parent
+                        .commands()
+                        .entity(all)
+                        .insert(CheckboxGroup { members });
+                },
+            );
+            parent
+                .commands()
+                .entity(dropdown)
+                .observe(on_dropdown_item_hovered);
+        })
+        .observe(on_combobutton_clicked)
+        .observe(on_multi_select_key)
+        .id()
+}
+
+/// Checks or unchecks an item, sending [`MultiSelectChanged`] if that changed anything.
+fn set_multi_select_item(
+    dropdown: Entity,
+    index: usize,
+    checked: bool,
+    selected: &mut Mut<SelectedItems>,
+    changed: &mut EventWriter<MultiSelectChanged>,
+) {
+    if selected.contains(&index) == checked {
+        return;
+    }
+    if checked {
+        selected.insert(index);
+    } else {
+        selected.remove(&index);
+    }
+    changed.write(MultiSelectChanged {
+        dropdown,
+        index,
+        checked,
+        selected: selected.0.clone(),
+    });
+}
+
+fn toggle_multi_select_item(
+    dropdown: Entity,
+    index: usize,
+    selected: &mut Mut<SelectedItems>,
+    changed: &mut EventWriter<MultiSelectChanged>,
+) {
+    let checked = !selected.contains(&index);
+    set_multi_select_item(dropdown, index, checked, selected, changed);
+}
+
+/// Item checkboxes toggle themselves when clicked. This takes the change over
+/// into the dropdown and gives it the focus back, leaving the popup open.
+fn on_multi_select_item_checked(
+    mut events: EventReader<CheckboxChanged>,
+    index_query: Query<&DropdownItemIndex>,
+    parent_query: Query<&ChildOf>,
+    popup_query: Query<&Popup, With<DropdownMenuMenu>>,
+    mut selected_query: Query<&mut SelectedItems>,
+    mut focus: ResMut<InputFocus>,
+    mut changed: EventWriter<MultiSelectChanged>,
+) {
+    for event in events.read() {
+        let Ok(index) = index_query.get(event.checkbox) else {
+            continue;
+        };
+        let Some(dropdown) = parent_query
+            .iter_ancestors(event.checkbox)
+            .find_map(|e| popup_query.get(e).ok())
+            .map(|popup| popup.owner)
+        else {
+            continue;
+        };
+        let Ok(mut selected) = selected_query.get_mut(dropdown) else {
+            continue;
+        };
+        let checked = event.checked.is_on();
+        set_multi_select_item(dropdown, index.0, checked, &mut selected, &mut changed);
+        if focus.0 != Some(dropdown) {
+            focus.0 = Some(dropdown);
+        }
+    }
+}
+
+/// Enter, Space or Down open the popup. While it's open, Up/Down/Home/End move
+/// the highlight, Space toggles the highlighted item and Enter closes the popup.
+fn on_multi_select_key(
+    mut trigger: Trigger<FocusedInput<KeyboardInput>>,
+    mut multi_select_query: Query<(
+        &mut SelectedItems,
+        &mut HighlightedItem,
+        &ComboboxItems,
+        &Children,
+    )>,
+    dropdown_query: Query<(), With<DropdownMenuMenu>>,
+    mut popups: Popups,
+    mut changed: EventWriter<MultiSelectChanged>,
+) {
+    let input = &trigger.event().input;
+    if input.state != ButtonState::Pressed {
+        return;
+    }
+    let key_code = input.key_code;
+    let entity = trigger.target();
+    let Ok((mut selected, mut highlighted, items, children)) = multi_select_query.get_mut(entity)
+    else {
+        return;
+    };
+    let Some(dropdown) = children.iter().find(|c| dropdown_query.contains(*c)) else {
+        return;
+    };
+    if !popups.is_open(dropdown) {
+        if matches!(
+            key_code,
+            KeyCode::Enter | KeyCode::NumpadEnter | KeyCode::Space | KeyCode::ArrowDown
+        ) {
+            trigger.propagate(false);
+            popups.open(dropdown);
+            let first = selected.iter().next().copied().unwrap_or(0);
+            highlighted.set_if_neq(HighlightedItem(Some(first)));
+        }
+        return;
+    }
+    let current = highlighted.unwrap_or(0);
+    let last = items.len().saturating_sub(1);
+    let next = match key_code {
+        KeyCode::ArrowUp => current.saturating_sub(1),
+        KeyCode::ArrowDown => (current + 1).min(last),
+        KeyCode::Home => 0,
+        KeyCode::End => last,
+        KeyCode::Space => {
+            trigger.propagate(false);
+            if current < items.len() {
+                toggle_multi_select_item(entity, current, &mut selected, &mut changed);
+            }
+            return;
+        }
+        KeyCode::Enter | KeyCode::NumpadEnter => {
+            trigger.propagate(false);
+            popups.close(dropdown);
+            return;
+        }
+        _ => return,
+    };
+    trigger.propagate(false);
+    highlighted.set_if_neq(HighlightedItem(Some(next)));
+}
+
+/// Updates the summary and the check marks of multi-select dropdowns.
+#[allow(clippy::type_complexity)]
+fn update_multi_selects(
+    multi_select_query: Query<
+        (Entity, &SelectedItems, &ComboboxItems, &Children),
+        (With<MultiSelect>, Changed<SelectedItems>),
+    >,
+    children_query: Query<&Children>,
+    mut items: Query<(&DropdownItemIndex, &mut Checked)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (entity, selected, labels, children) in multi_select_query {
+        set_box_text(
+            children,
+            &mut text_query,
+            multi_select_summary(selected, labels),
+        );
+        let mut item_iter = items.iter_many_mut(children_query.iter_descendants(entity));
+        while let Some((index, mut checked)) = item_iter.fetch_next() {
+            checked.set_if_neq(Checked::from(selected.contains(&index.0)));
+        }
+    }
+}
+
+pub struct MultiSelectPlugin;
+
+impl Plugin for MultiSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<MultiSelectChanged>().add_systems(
+            Update,
+            (
+                on_multi_select_item_checked.run_if(on_event::<CheckboxChanged>),
+                update_multi_selects,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uistuff::settings::DropdownMenuItem;
+    use bevy::ecs::system::RunSystemOnce;
+
+    fn items(labels: &[&str]) -> ComboboxItems {
+        ComboboxItems(
+            labels
+                .iter()
+                .map(|label| DropdownMenuItem {
+                    label: label.to_string(),
+                })
+                .collect(),
+        )
+    }
+
+    fn selected(indices: &[usize]) -> SelectedItems {
+        SelectedItems(indices.iter().copied().collect())
+    }
+
+    #[test]
+    fn summary() {
+        let items = items(&["Music", "Effects", "Voice"]);
+        assert_eq!(multi_select_summary(&selected(&[]), &items), "None");
+        assert_eq!(multi_select_summary(&selected(&[1]), &items), "Effects");
+        assert_eq!(
+            multi_select_summary(&selected(&[0, 2]), &items),
+            "2 selected"
+        );
+        assert_eq!(multi_select_summary(&selected(&[0, 1, 2]), &items), "All");
+    }
+
+    /// A multi-select with its summary text and one item checkbox per label.
+    fn spawn_test_multi_select(app: &mut App, labels: &[&str]) -> (Entity, Entity, Vec<Entity>) {
+        let world = app.world_mut();
+        let multi_select = world
+            .spawn((MultiSelect, SelectedItems::default(), items(labels)))
+            .id();
+        let summary = world.spawn((Text::default(), ChildOf(multi_select))).id();
+        let dropdown = world
+            .spawn((
+                DropdownMenuMenu,
+                Popup {
+                    owner: multi_select,
+                },
+                ChildOf(multi_select),
+            ))
+            .id();
+        let item_entities = (0..labels.len())
+            .map(|index| {
+                world
+                    .spawn((DropdownItemIndex(index), Checked::Off, ChildOf(dropdown)))
+                    .id()
+            })
+            .collect();
+        (multi_select, summary, item_entities)
+    }
+
+    fn test_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, MultiSelectPlugin))
+            .add_event::<CheckboxChanged>()
+            .init_resource::<InputFocus>();
+        app
+    }
+
+    fn sent_changes(app: &App) -> Vec<(usize, bool)> {
+        let events = app.world().resource::<Events<MultiSelectChanged>>();
+        events
+            .get_cursor()
+            .read(events)
+            .map(|event| (event.index, event.checked))
+            .collect()
+    }
+
+    #[test]
+    fn toggling_updates_marks_and_summary() {
+        let mut app = test_app();
+        let (multi_select, summary, item_entities) =
+            spawn_test_multi_select(&mut app, &["Music", "Effects"]);
+        let toggle = move |index: usize| {
+            move |mut query: Query<&mut SelectedItems>,
+                  mut changed: EventWriter<MultiSelectChanged>| {
+                let mut selected = query.get_mut(multi_select).unwrap();
+                toggle_multi_select_item(multi_select, index, &mut selected, &mut changed);
+            }
+        };
+        app.world_mut().run_system_once(toggle(1)).unwrap();
+        app.update();
+        assert_eq!(sent_changes(&app), vec![(1, true)]);
+        assert_eq!(app.world().get::<Text>(summary).unwrap().0, "Effects");
+        assert_eq!(
+            app.world().get::<Checked>(item_entities[1]),
+            Some(&Checked::On)
+        );
+        assert_eq!(
+            app.world().get::<Checked>(item_entities[0]),
+            Some(&Checked::Off)
+        );
+
+        app.world_mut().run_system_once(toggle(1)).unwrap();
+        app.update();
+        assert_eq!(app.world().get::<Text>(summary).unwrap().0, "None");
+        assert_eq!(
+            app.world().get::<Checked>(item_entities[1]),
+            Some(&Checked::Off)
+        );
+    }
+
+    #[test]
+    fn clicked_item_checkbox_updates_selection() {
+        let mut app = test_app();
+        let (multi_select, summary, item_entities) =
+            spawn_test_multi_select(&mut app, &["Music", "Effects"]);
+        app.world_mut().send_event(CheckboxChanged {
+            checkbox: item_entities[0],
+            checked: Checked::On,
+        });
+        app.update();
+        assert_eq!(sent_changes(&app), vec![(0, true)]);
+        assert_eq!(
+            app.world().get::<SelectedItems>(multi_select),
+            Some(&selected(&[0]))
+        );
+        assert_eq!(app.world().get::<Text>(summary).unwrap().0, "Music");
+        assert_eq!(app.world().resource::<InputFocus>().0, Some(multi_select));
+    }
+}
@@ -0,0 +1,131 @@
+use bevy::{
+    asset::ron::{self, ser::PrettyConfig},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    audio::AudioSettings,
+    input_map::InputMap,
+    toast::{Severity, ShowToast},
+    video::VideoSettings,
+};
+
+/// File the settings are stored in, relative to the working directory.
+pub const SETTINGS_FILE: &str = "settings.ron";
+
+/// Everything that gets written to [`SETTINGS_FILE`].
+/// Missing sections fall back to their defaults, so older files keep loading.
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+struct SavedSettings {
+    video: VideoSettings,
+    audio: AudioSettings,
+    input: InputMap,
+}
+
+/// Send this to write the current settings resources to disk.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SaveSettings;
+
+fn read_settings() -> SavedSettings {
+    let contents = match std::fs::read_to_string(SETTINGS_FILE) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            info!("No {SETTINGS_FILE} found, using default settings");
+            return SavedSettings::default();
+        }
+        Err(e) => {
+            warn!("Couldn't read {SETTINGS_FILE}: {e}");
+            return SavedSettings::default();
+        }
+    };
+    ron::from_str(&contents).unwrap_or_else(|e| {
+        warn!("Couldn't parse {SETTINGS_FILE}, using default settings: {e}");
+        SavedSettings::default()
+    })
+}
+
+/// Inserts the settings resources, before any startup system reads them.
+fn load_settings(mut commands: Commands) {
+    let saved = read_settings();
+    commands.insert_resource(saved.video);
+    commands.insert_resource(saved.audio);
+    commands.insert_resource(saved.input.with_missing_defaults());
+}
+
+fn save_settings(
+    video: Res<VideoSettings>,
+    audio: Res<AudioSettings>,
+    input: Res<InputMap>,
+    mut toasts: EventWriter<ShowToast>,
+) {
+    let saved = SavedSettings {
+        video: *video,
+        audio: *audio,
+        input: input.clone(),
+    };
+    let result = ron::ser::to_string_pretty(&saved, PrettyConfig::default())
+        .map_err(|e| e.to_string())
+        .and_then(|s| std::fs::write(SETTINGS_FILE, s).map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => {
+            info!("Settings saved to {SETTINGS_FILE}");
+            toasts.write(ShowToast::new(Severity::Success, "Settings saved"));
+        }
+        Err(e) => {
+            warn!("Couldn't save settings: {e}");
+            toasts.write(
+                ShowToast::new(Severity::Error, "Couldn't save settings").with_duration(8.0),
+            );
+        }
+    }
+}
+
+/// Loads the settings resources from [`SETTINGS_FILE`] in `PreStartup`
+/// and saves them back on [`SaveSettings`].
+pub struct SettingsStoragePlugin;
+
+impl Plugin for SettingsStoragePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SaveSettings>()
+            .add_systems(PreStartup, load_settings)
+            .add_systems(Update, save_settings.run_if(on_event::<SaveSettings>));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uistuff::{
+        input_map::Binding,
+        video::{QualityPreset, WindowModeSetting},
+    };
+
+    #[test]
+    fn settings_round_trip() {
+        let mut saved = SavedSettings::default();
+        saved.video.window_mode = WindowModeSetting::Borderless;
+        saved.video.resolution = (1920, 1080);
+        saved.video.ui_scale = 1.25;
+        QualityPreset::High.apply(&mut saved.video);
+        saved.audio.music = 0.3;
+        saved
+            .input
+            .set_binding("Interact", 1, Some(Binding::Mouse(MouseButton::Right)));
+        let text = ron::ser::to_string_pretty(&saved, PrettyConfig::default()).unwrap();
+        let loaded: SavedSettings = ron::from_str(&text).unwrap();
+        assert_eq!(loaded.video, saved.video);
+        assert_eq!(loaded.audio, saved.audio);
+        assert_eq!(loaded.input, saved.input);
+    }
+
+    #[test]
+    fn missing_sections_use_defaults() {
+        let loaded: SavedSettings = ron::from_str("(video: (ui_scale: 2.0))").unwrap();
+        assert_eq!(loaded.video.ui_scale, 2.0);
+        assert_eq!(loaded.video.resolution, VideoSettings::default().resolution);
+        assert_eq!(loaded.audio, AudioSettings::default());
+        assert_eq!(loaded.input, InputMap::default());
+    }
+}
@@ -0,0 +1,301 @@
+use crate::uistuff::utils::*;
+use bevy::{ecs::system::SystemParam, input_focus::InputFocus, prelude::*};
+
+use super::modal::{Modal, ModalStack, no_modal_open};
+
+/// Z-index of the lowest open popup. Every popup opened on top of it gets the
+/// next index, so nested popups always cover the ones they were opened from.
+/// Popups opened from inside a modal start right above that modal instead.
+pub const POPUP_Z_INDEX: i32 = 100;
+
+/// A node that is only shown while it's open, above the rest of the UI.
+/// Spawn it hidden and open or close it through [`Popups`].
+/// It is despawned together with its owner.
+#[derive(Component, Clone, Copy, Debug)]
+#[require(GlobalZIndex)]
+pub struct Popup {
+    pub owner: Entity,
+}
+
+/// `entity` followed by everything it is shown inside of, going from popups
+/// to their owners, since some popups are spawned at the root.
+fn containers<'a>(
+    entity: Entity,
+    popups: &'a Query<&Popup>,
+    parents: &'a Query<&ChildOf>,
+) -> impl Iterator<Item = Entity> + 'a {
+    std::iter::successors(Some(entity), |e| {
+        popups
+            .get(*e)
+            .map(|popup| popup.owner)
+            .or_else(|_| parents.get(*e).map(ChildOf::parent))
+            .ok()
+    })
+}
+
+/// Open popups, from the bottom to the topmost one.
+#[derive(Resource, Default, Debug, Deref)]
+pub struct PopupStack(Vec<Entity>);
+
+/// Opens and closes popups, keeping the stack consistent with the UI hierarchy.
+#[derive(SystemParam)]
+pub struct Popups<'w, 's> {
+    stack: ResMut<'w, PopupStack>,
+    popups: Query<'w, 's, &'static Popup>,
+    parents: Query<'w, 's, &'static ChildOf>,
+}
+
+impl Popups<'_, '_> {
+    pub fn is_open(&self, popup: Entity) -> bool {
+        self.stack.contains(&popup)
+    }
+
+    pub fn top(&self) -> Option<Entity> {
+        self.stack.last().copied()
+    }
+
+    pub fn owner(&self, popup: Entity) -> Option<Entity> {
+        self.popups.get(popup).ok().map(|p| p.owner)
+    }
+
+    /// Whether `entity` is `node` itself or one of its descendants.
+    pub fn contains(&self, node: Entity, entity: Entity) -> bool {
+        std::iter::once(entity)
+            .chain(self.parents.iter_ancestors(entity))
+            .any(|e| e == node)
+    }
+
+    /// Whether `entity` is shown inside `container`, also through popups opened from it.
+    pub fn shown_in(&self, entity: Entity, container: Entity) -> bool {
+        containers(entity, &self.popups, &self.parents).any(|e| e == container)
+    }
+
+    /// Opens `popup` on top of the popups its owner is part of.
+    /// All other open popups are closed.
+    pub fn open(&mut self, popup: Entity) {
+        let Some(owner) = self.owner(popup) else {
+            warn!("Tried to open {popup}, which isn't a popup");
+            return;
+        };
+        if let Some(index) = self.stack.iter().position(|e| *e == popup) {
+            self.stack.0.truncate(index + 1);
+            return;
+        }
+        while let Some(top) = self.top() {
+            if self.contains(top, owner) {
+                break;
+            }
+            self.stack.0.pop();
+        }
+        self.stack.0.push(popup);
+    }
+
+    /// Closes `popup` together with the popups opened from it.
+    pub fn close(&mut self, popup: Entity) {
+        if let Some(index) = self.stack.iter().position(|e| *e == popup) {
+            self.stack.0.truncate(index);
+        }
+    }
+
+    pub fn toggle(&mut self, popup: Entity) {
+        if self.is_open(popup) {
+            self.close(popup);
+        } else {
+            self.open(popup);
+        }
+    }
+
+    /// Closes the topmost popup, returning it.
+    pub fn close_top(&mut self) -> Option<Entity> {
+        self.stack.0.pop()
+    }
+}
+
+/// Offset of a popup from the top left corner of its anchor, and the height it is
+/// limited to when it doesn't fit on screen. In physical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PopupPlacement {
+    pub offset: Vec2,
+    pub max_height: Option<f32>,
+}
+
+/// Opens the popup below its anchor, or above it when there's more room there.
+/// It is shifted sideways to stay on screen and gets shorter when neither side has
+/// enough room for all of it.
+pub fn place_popup(anchor: Rect, popup_size: Vec2, screen_size: Vec2) -> PopupPlacement {
+    let room_below = (screen_size.y - anchor.max.y).max(0.0);
+    let room_above = anchor.min.y.max(0.0);
+    let (top, max_height) = if popup_size.y <= room_below {
+        (anchor.height(), None)
+    } else if room_above > room_below {
+        let height = popup_size.y.min(room_above);
+        (-height, (height < popup_size.y).then_some(height))
+    } else {
+        (anchor.height(), Some(room_below))
+    };
+    let left = (anchor.min.x.min(screen_size.x - popup_size.x)).max(0.0) - anchor.min.x;
+    PopupPlacement {
+        offset: Vec2::new(left, top),
+        max_height,
+    }
+}
+
+/// Clicks outside of the topmost popup and its owner close that popup.
+/// While a modal is open, only popups opened from inside it are closed.
+fn close_popup_on_outside_click(
+    trigger: Trigger<Pointer<Click>>,
+    mut popups: Popups,
+    modals: Res<ModalStack>,
+) {
+    let clicked = trigger.event().target;
+    if trigger.target() != clicked {
+        return;
+    }
+    let Some(top) = popups.top() else {
+        return;
+    };
+    if modals
+        .top()
+        .is_some_and(|modal| !popups.shown_in(top, modal))
+    {
+        return;
+    }
+    let on_owner = popups
+        .owner(top)
+        .is_some_and(|owner| popups.contains(owner, clicked));
+    if !on_owner && !popups.contains(top, clicked) {
+        info!("Outside click detected!");
+        popups.close(top);
+    }
+}
+
+/// Escape closes the topmost popup and gives the focus back to its owner.
+/// An open modal takes Escape for itself.
+fn close_popup_on_escape(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut popups: Popups,
+    focusable: Query<(), With<Focusable>>,
+    mut focus: ResMut<InputFocus>,
+) {
+    if !keys.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    let Some(popup) = popups.close_top() else {
+        return;
+    };
+    if let Some(owner) = popups.owner(popup)
+        && focusable.contains(owner)
+        && focus.0 != Some(owner)
+    {
+        focus.0 = Some(owner);
+    }
+}
+
+fn despawn_orphaned_popups(
+    mut commands: Commands,
+    popups: Query<(Entity, &Popup)>,
+    entities: Query<()>,
+    mut stack: ResMut<PopupStack>,
+) {
+    let is_orphan = |popup: &Popup| !entities.contains(popup.owner);
+    for (entity, popup) in popups {
+        if is_orphan(popup) {
+            commands.entity(entity).try_despawn();
+        }
+    }
+    let is_alive = |e: &Entity| popups.get(*e).is_ok_and(|(_, p)| !is_orphan(p));
+    if !stack.iter().all(is_alive) {
+        stack.0.retain(is_alive);
+    }
+}
+
+/// Shows the open popups in stacking order and hides the others.
+fn update_popup_visibility(
+    stack: Res<PopupStack>,
+    nodes: Query<(Entity, &Popup, &mut Node, &mut GlobalZIndex)>,
+    popups: Query<&Popup>,
+    parents: Query<&ChildOf>,
+    modals: Query<&GlobalZIndex, (With<Modal>, Without<Popup>)>,
+) {
+    for (entity, popup, mut node, mut z_index) in nodes {
+        match stack.iter().position(|e| *e == entity) {
+            Some(index) => {
+                let base = containers(popup.owner, &popups, &parents)
+                    .find_map(|e| modals.get(e).ok())
+                    .map_or(POPUP_Z_INDEX, |modal| modal.0 + 1);
+                node.display = Display::Flex;
+                z_index.set_if_neq(GlobalZIndex(base + index as i32));
+            }
+            None => node.display = Display::None,
+        }
+    }
+}
+
+pub struct PopupPlugin;
+
+impl Plugin for PopupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PopupStack>()
+            .add_observer(close_popup_on_outside_click)
+            .add_systems(
+                Update,
+                (
+                    close_popup_on_escape.run_if(no_modal_open),
+                    despawn_orphaned_popups,
+                    update_popup_visibility.run_if(resource_changed::<PopupStack>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Vec2 = Vec2::new(800.0, 600.0);
+
+    fn anchor(x: f32, y: f32) -> Rect {
+        Rect::new(x, y, x + 100.0, y + 30.0)
+    }
+
+    #[test]
+    fn fits_below() {
+        let placement = place_popup(anchor(100.0, 100.0), Vec2::new(100.0, 200.0), SCREEN);
+        assert_eq!(placement.offset, Vec2::new(0.0, 30.0));
+        assert_eq!(placement.max_height, None);
+    }
+
+    #[test]
+    fn flips_above() {
+        let placement = place_popup(anchor(100.0, 500.0), Vec2::new(100.0, 200.0), SCREEN);
+        assert_eq!(placement.offset, Vec2::new(0.0, -200.0));
+        assert_eq!(placement.max_height, None);
+    }
+
+    #[test]
+    fn shrinks_above_when_there_is_more_room_there() {
+        let placement = place_popup(anchor(100.0, 400.0), Vec2::new(100.0, 700.0), SCREEN);
+        assert_eq!(placement.offset, Vec2::new(0.0, -400.0));
+        assert_eq!(placement.max_height, Some(400.0));
+    }
+
+    #[test]
+    fn shrinks_below_when_there_is_more_room_there() {
+        let placement = place_popup(anchor(100.0, 100.0), Vec2::new(100.0, 700.0), SCREEN);
+        assert_eq!(placement.offset, Vec2::new(0.0, 30.0));
+        assert_eq!(placement.max_height, Some(470.0));
+    }
+
+    #[test]
+    fn shifts_left_to_stay_on_screen() {
+        let placement = place_popup(anchor(700.0, 100.0), Vec2::new(200.0, 100.0), SCREEN);
+        assert_eq!(placement.offset, Vec2::new(-100.0, 30.0));
+    }
+
+    #[test]
+    fn popup_wider_than_the_screen_starts_at_its_left_edge() {
+        let placement = place_popup(anchor(100.0, 100.0), Vec2::new(1000.0, 100.0), SCREEN);
+        assert_eq!(placement.offset, Vec2::new(-100.0, 30.0));
+    }
+}
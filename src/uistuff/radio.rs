@@ -0,0 +1,303 @@
+use crate::uistuff::utils::*;
+use bevy::{
+    ecs::relationship::RelatedSpawnerCommands,
+    input::{ButtonState, gamepad::GamepadButtonChangedEvent, keyboard::KeyboardInput},
+    input_focus::FocusedInput,
+    prelude::*,
+};
+
+use super::{
+    config::{StyleColor, UiStyle},
+    layouts::{NodeModifier, vertical_stack, vertically_centered},
+};
+
+const CIRCLE_SIZE: f32 = 24.0;
+const DOT_SIZE: f32 = 12.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RadioLayout {
+    #[default]
+    Vertical,
+    Horizontal,
+}
+
+/// A group of mutually exclusive options. The group itself stores the selection,
+/// so exactly one option is always selected.
+#[derive(Component, Clone, Debug, Default)]
+#[require(Focusable)]
+pub struct RadioGroup {
+    pub options: Vec<String>,
+    pub selected: usize,
+}
+
+impl RadioGroup {
+    /// An out of range `selected` selects the last option.
+    pub fn new(options: impl IntoIterator<Item = impl Into<String>>, selected: usize) -> Self {
+        let options: Vec<String> = options.into_iter().map(Into::into).collect();
+        Self {
+            selected: selected.min(options.len().saturating_sub(1)),
+            options,
+        }
+    }
+
+    pub fn selected_value(&self) -> Option<&str> {
+        self.options.get(self.selected).map(String::as_str)
+    }
+}
+
+/// Sent when the user selects a different option of a [`RadioGroup`].
+#[derive(Event, Clone, Debug)]
+pub struct RadioChanged {
+    pub group: Entity,
+    pub index: usize,
+    pub value: String,
+}
+
+/// One option of a radio group, holding its index.
+#[derive(Component, Clone, Copy, Debug, Deref)]
+pub struct RadioOption(pub usize);
+
+#[derive(Component)]
+struct RadioDot;
+
+fn dot_display(selected: bool) -> Display {
+    if selected {
+        Display::Flex
+    } else {
+        Display::None
+    }
+}
+
+fn radio_option(
+    parent: &mut RelatedSpawnerCommands<ChildOf>,
+    label: &str,
+    index: usize,
+    selected: bool,
+    font: Handle<Font>,
+    ui_style: &UiStyle,
+) {
+    vertically_centered(
+        parent,
+        (RadioOption(index), Button),
+        NodeModifier::new(),
+        |parent| {
+            parent.spawn((
+                Node {
+                    width: Val::Px(CIRCLE_SIZE),
+                    height: Val::Px(CIRCLE_SIZE),
+                    margin: UiRect::all(Val::Px(ui_style.spacing.small)),
+                    border: UiRect::all(Val::Px(ui_style.borders.normal)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                BorderRadius::MAX,
+                StyleColor::NormalBack.border(ui_style),
+                StyleColor::NormalFore.background(ui_style),
+                children![(
+                    RadioDot,
+                    Node {
+                        display: dot_display(selected),
+                        width: Val::Px(DOT_SIZE),
+                        height: Val::Px(DOT_SIZE),
+                        ..Default::default()
+                    },
+                    BorderRadius::MAX,
+                    StyleColor::NormalBack.background(ui_style),
+                )],
+            ));
+            parent.spawn((
+                Text::new(label),
+                StyleColor::HoverFore.text(ui_style),
+                TextFont {
+                    font,
+                    font_size: ui_style.font_sizes.body,
+                    ..Default::default()
+                },
+                Node {
+                    margin: UiRect::right(Val::Px(ui_style.spacing.medium)),
+                    ..Default::default()
+                },
+            ));
+        },
+    );
+}
+
+pub fn spawn_radio_group(
+    commands: impl GenericSpawner,
+    extra_components: impl Bundle,
+    group: RadioGroup,
+    layout: RadioLayout,
+    node_modifier: NodeModifier,
+    ui_assets: &UIAssets,
+    ui_style: &UiStyle,
+) -> Entity {
+    let font = ui_assets
+        .font
+        .clone()
+        .expect("Font should be loaded already");
+    let options = group.options.clone();
+    let selected = group.selected;
+    let spawn_options = |parent: &mut RelatedSpawnerCommands<ChildOf>| {
+        for (index, label) in options.iter().enumerate() {
+            radio_option(
+                parent,
+                label,
+                index,
+                index == selected,
+                font.clone(),
+                ui_style,
+            );
+        }
+    };
+    let extra_components = (extra_components, group, Name::new("RadioGroup"));
+    match layout {
+        RadioLayout::Vertical => {
+            vertical_stack(commands, extra_components, node_modifier, spawn_options)
+        }
+        RadioLayout::Horizontal => {
+            vertically_centered(commands, extra_components, node_modifier, spawn_options)
+        }
+    }
+}
+
+fn select_option(
+    entity: Entity,
+    group: &mut RadioGroup,
+    index: usize,
+    changed: &mut EventWriter<RadioChanged>,
+) {
+    if index >= group.options.len() || index == group.selected {
+        return;
+    }
+    group.selected = index;
+    changed.write(RadioChanged {
+        group: entity,
+        index,
+        value: group.options[index].clone(),
+    });
+}
+
+fn on_radio_option_clicked(
+    mut trigger: Trigger<Pointer<Click>>,
+    options: Query<(&RadioOption, &ChildOf)>,
+    mut groups: Query<&mut RadioGroup>,
+    mut changed: EventWriter<RadioChanged>,
+) {
+    let Ok((option, child_of)) = options.get(trigger.target()) else {
+        return;
+    };
+    let Ok(mut group) = groups.get_mut(child_of.parent()) else {
+        return;
+    };
+    trigger.propagate(false);
+    select_option(child_of.parent(), &mut group, option.0, &mut changed);
+}
+
+/// Moves the selection by `offset`, wrapping around at both ends.
+fn select_relative(
+    entity: Entity,
+    group: &mut RadioGroup,
+    offset: isize,
+    changed: &mut EventWriter<RadioChanged>,
+) {
+    let count = group.options.len() as isize;
+    if count == 0 {
+        return;
+    }
+    let index = (group.selected as isize + offset).rem_euclid(count) as usize;
+    select_option(entity, group, index, changed);
+}
+
+fn on_radio_key(
+    mut trigger: Trigger<FocusedInput<KeyboardInput>>,
+    mut groups: Query<&mut RadioGroup>,
+    mut changed: EventWriter<RadioChanged>,
+) {
+    let entity = trigger.target();
+    let input = &trigger.event().input;
+    if input.state != ButtonState::Pressed {
+        return;
+    }
+    let Ok(mut group) = groups.get_mut(entity) else {
+        return;
+    };
+    let offset = match input.key_code {
+        KeyCode::ArrowUp | KeyCode::ArrowLeft => -1,
+        KeyCode::ArrowDown | KeyCode::ArrowRight => 1,
+        _ => return,
+    };
+    trigger.propagate(false);
+    select_relative(entity, &mut group, offset, &mut changed);
+}
+
+fn on_radio_gamepad_button(
+    mut trigger: Trigger<FocusedInput<GamepadButtonChangedEvent>>,
+    mut groups: Query<&mut RadioGroup>,
+    mut changed: EventWriter<RadioChanged>,
+) {
+    let entity = trigger.target();
+    let input = &trigger.event().input;
+    if input.state != ButtonState::Pressed {
+        return;
+    }
+    let Ok(mut group) = groups.get_mut(entity) else {
+        return;
+    };
+    let offset = match input.button {
+        GamepadButton::DPadUp | GamepadButton::DPadLeft => -1,
+        GamepadButton::DPadDown | GamepadButton::DPadRight => 1,
+        _ => return,
+    };
+    trigger.propagate(false);
+    select_relative(entity, &mut group, offset, &mut changed);
+}
+
+fn update_radio_dots(
+    groups: Query<(Entity, &RadioGroup), Changed<RadioGroup>>,
+    children_query: Query<&Children>,
+    options: Query<&RadioOption>,
+    mut dots: Query<&mut Node, With<RadioDot>>,
+) {
+    for (entity, group) in groups {
+        for option_entity in children_query.iter_descendants(entity) {
+            let Ok(option) = options.get(option_entity) else {
+                continue;
+            };
+            let display = dot_display(option.0 == group.selected);
+            for dot in children_query.iter_descendants(option_entity) {
+                if let Ok(mut node) = dots.get_mut(dot)
+                    && node.display != display
+                {
+                    node.display = display;
+                }
+            }
+        }
+    }
+}
+
+pub struct RadioPlugin;
+
+impl Plugin for RadioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RadioChanged>()
+            .add_observer(on_radio_option_clicked)
+            .add_observer(on_radio_key)
+            .add_observer(on_radio_gamepad_button)
+            .add_systems(Update, update_radio_dots);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_clamps_selection() {
+        let group = RadioGroup::new(["a", "b", "c"], 1);
+        assert_eq!(group.selected_value(), Some("b"));
+        let group = RadioGroup::new(["a", "b", "c"], 7);
+        assert_eq!(group.selected, 2);
+        assert_eq!(group.selected_value(), Some("c"));
+    }
+}
@@ -0,0 +1,504 @@
+use crate::uistuff::utils::*;
+use bevy::{
+    input::{ButtonState, keyboard::KeyboardInput},
+    input_focus::{FocusedInput, InputFocus},
+    prelude::*,
+};
+
+use super::{
+    config::{StyleRole, UiStyle, VisualState},
+    layouts::NodeModifier,
+    popup::{Popup, Popups},
+    settings::{
+        ComboboxChanged, ComboboxItems, DropdownItemIndex, DropdownMenu, DropdownMenuItem,
+        DropdownMenuMenu, HighlightedItem, PreviousLayout, SelectedIndex, dropdown_menu,
+        on_dropdown_item_clicked, on_dropdown_item_hovered, select_combobox_item,
+    },
+    text_input::{TextInput, TextInputChanged, text_input_box},
+};
+
+/// How a [`SearchableCombobox`] matches the typed text against its items.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Items containing the typed text.
+    #[default]
+    Substring,
+    /// Items containing the typed characters in order, with anything in between.
+    Fuzzy,
+}
+
+/// A combobox whose closed state is a text input. Typing filters the popup,
+/// Up/Down move through the matching items and Enter picks one.
+#[derive(Component, Clone, Copy, Debug, Default)]
+#[require(HighlightedItem)]
+pub struct SearchableCombobox {
+    pub mode: FilterMode,
+}
+
+/// Items of a searchable combobox matching its text, each with the positions
+/// of the matched characters in its label.
+#[derive(Component, Clone, Debug, Default)]
+pub(crate) struct FilteredItems(Vec<(usize, Vec<usize>)>);
+
+impl FilteredItems {
+    fn all(count: usize) -> Self {
+        Self((0..count).map(|i| (i, Vec::new())).collect())
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        self.0.iter().any(|(i, _)| *i == index)
+    }
+}
+
+/// A span of a dropdown item label matching the filter text.
+#[derive(Component)]
+struct MatchedText;
+
+/// Positions of the characters of `label` matching `typed`, ignoring case.
+fn filter_match(label: &str, typed: &str, mode: FilterMode) -> Option<Vec<usize>> {
+    let fold = |s: &str| -> Vec<char> {
+        s.chars()
+            .map(|c| c.to_lowercase().next().unwrap_or(c))
+            .collect()
+    };
+    let (label, typed) = (fold(label), fold(typed));
+    if typed.is_empty() {
+        return Some(Vec::new());
+    }
+    match mode {
+        FilterMode::Substring => label
+            .windows(typed.len())
+            .position(|window| window == typed.as_slice())
+            .map(|start| (start..start + typed.len()).collect()),
+        FilterMode::Fuzzy => {
+            let mut chars = label.iter().enumerate();
+            typed
+                .iter()
+                .map(|t| chars.find(|(_, c)| *c == t).map(|(i, _)| i))
+                .collect()
+        }
+    }
+}
+
+fn filter_items(items: &[DropdownMenuItem], typed: &str, mode: FilterMode) -> FilteredItems {
+    FilteredItems(
+        items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| filter_match(&item.label, typed, mode).map(|m| (i, m)))
+            .collect(),
+    )
+}
+
+/// Spawns a combobox that can be typed into to filter its items.
+/// The popup is spawned at the root, since the text input clips its children.
+/// Like [`spawn_combobox`](super::settings::spawn_combobox), an out of range
+/// `selected` selects the last item.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_searchable_combobox(
+    mut commands: impl GenericSpawner,
+    extra_components: impl Bundle,
+    menu: DropdownMenu,
+    selected: usize,
+    mode: FilterMode,
+    node_modifier: NodeModifier,
+    ui_assets: &UIAssets,
+    ui_style: &UiStyle,
+) -> Entity {
+    let font = ui_assets
+        .font
+        .clone()
+        .expect("Font should be loaded already");
+    let selected = selected.min(menu.items.len().saturating_sub(1));
+    let selected_item = menu
+        .items
+        .get(selected)
+        .map(|item| item.label.clone())
+        .unwrap_or_default();
+    let mut combobox = commands.generic_spawn((
+        extra_components,
+        text_input_box(TextInput::new(selected_item), font, node_modifier, ui_style),
+        SearchableCombobox { mode },
+        SelectedIndex(selected),
+        ComboboxItems(menu.items.clone()),
+        FilteredItems::all(menu.items.len()),
+        PreviousLayout::default(),
+    ));
+    combobox
+        .observe(on_searchable_combobox_clicked)
+        .observe(on_searchable_combobox_key);
+    let owner = combobox.id();
+    let mut commands = combobox.commands();
+    let dropdown = dropdown_menu(
+        &mut commands,
+        (DropdownMenuMenu, Name::new("Dropdown"), Popup { owner }),
+        NodeModifier::new().force_absolute_pos().spawn_hidden(),
+        menu,
+        ui_style,
+    );
+    commands
+        .entity(dropdown)
+        .observe(on_dropdown_item_clicked)
+        .observe(on_dropdown_item_hovered);
+    owner
+}
+
+/// The dropdown popup belonging to `combobox`.
+fn combobox_dropdown(
+    combobox: Entity,
+    dropdowns: &Query<(Entity, &Popup), With<DropdownMenuMenu>>,
+) -> Option<Entity> {
+    dropdowns
+        .iter()
+        .find(|(_, popup)| popup.owner == combobox)
+        .map(|(e, _)| e)
+}
+
+/// The matching item `offset` places away from the highlighted one.
+fn move_filtered_highlight(
+    filtered: &FilteredItems,
+    current: Option<usize>,
+    offset: isize,
+) -> Option<usize> {
+    let position = current.and_then(|c| filtered.0.iter().position(|(i, _)| *i == c));
+    let next = match position {
+        Some(position) => (position as isize + offset).clamp(0, filtered.0.len() as isize - 1),
+        None if offset < 0 => filtered.0.len() as isize - 1,
+        None => 0,
+    };
+    filtered.0.get(next as usize).map(|(i, _)| *i)
+}
+
+/// Up and Down open the popup and move through the matching items,
+/// Enter picks the highlighted one. Typing is left to the text input.
+fn on_searchable_combobox_key(
+    mut trigger: Trigger<FocusedInput<KeyboardInput>>,
+    mut combobox_query: Query<(
+        &mut SelectedIndex,
+        &mut HighlightedItem,
+        &FilteredItems,
+        &ComboboxItems,
+    )>,
+    dropdowns: Query<(Entity, &Popup), With<DropdownMenuMenu>>,
+    mut popups: Popups,
+    mut changed: EventWriter<ComboboxChanged>,
+) {
+    let input = &trigger.event().input;
+    if input.state != ButtonState::Pressed {
+        return;
+    }
+    let key_code = input.key_code;
+    let combobox = trigger.target();
+    let Ok((mut selected, mut highlighted, filtered, items)) = combobox_query.get_mut(combobox)
+    else {
+        return;
+    };
+    let Some(dropdown) = combobox_dropdown(combobox, &dropdowns) else {
+        return;
+    };
+    let offset = match key_code {
+        KeyCode::ArrowUp => -1,
+        KeyCode::ArrowDown => 1,
+        KeyCode::Enter | KeyCode::NumpadEnter if popups.is_open(dropdown) => {
+            trigger.propagate(false);
+            popups.close(dropdown);
+            if let Some(index) = highlighted.0.filter(|i| filtered.contains(*i)) {
+                select_combobox_item(combobox, index, &mut selected, items, &mut changed);
+            }
+            return;
+        }
+        _ => return,
+    };
+    trigger.propagate(false);
+    if filtered.0.is_empty() {
+        return;
+    }
+    let next = if popups.is_open(dropdown) {
+        move_filtered_highlight(filtered, highlighted.0, offset)
+    } else {
+        popups.open(dropdown);
+        Some(selected.0)
+            .filter(|i| filtered.contains(*i))
+            .or_else(|| move_filtered_highlight(filtered, None, offset))
+    };
+    highlighted.set_if_neq(HighlightedItem(next));
+}
+
+/// Clicking a searchable combobox toggles its popup with all items in it,
+/// selecting the text so typing replaces it.
+fn on_searchable_combobox_clicked(
+    mut trigger: Trigger<Pointer<Click>>,
+    mut combobox_query: Query<(
+        &SelectedIndex,
+        &mut HighlightedItem,
+        &mut FilteredItems,
+        &mut TextInput,
+        &ComboboxItems,
+    )>,
+    dropdowns: Query<(Entity, &Popup), With<DropdownMenuMenu>>,
+    mut popups: Popups,
+) {
+    let combobox = trigger.target();
+    let Ok((selected, mut highlighted, mut filtered, mut input, items)) =
+        combobox_query.get_mut(combobox)
+    else {
+        return;
+    };
+    let Some(dropdown) = combobox_dropdown(combobox, &dropdowns) else {
+        return;
+    };
+    trigger.propagate(false);
+    if popups.is_open(dropdown) {
+        popups.close(dropdown);
+        return;
+    }
+    popups.open(dropdown);
+    input.select_all();
+    *filtered = FilteredItems::all(items.len());
+    highlighted.set_if_neq(HighlightedItem(Some(selected.0)));
+}
+
+/// Filters searchable comboboxes as their text is edited, opening the popup
+/// on the first match and closing it when nothing matches.
+fn filter_searchable_comboboxes(
+    mut events: EventReader<TextInputChanged>,
+    mut combobox_query: Query<(
+        &SearchableCombobox,
+        &ComboboxItems,
+        &mut FilteredItems,
+        &mut HighlightedItem,
+    )>,
+    dropdowns: Query<(Entity, &Popup), With<DropdownMenuMenu>>,
+    mut popups: Popups,
+) {
+    for event in events.read() {
+        let Ok((combobox, items, mut filtered, mut highlighted)) =
+            combobox_query.get_mut(event.input)
+        else {
+            continue;
+        };
+        let Some(dropdown) = combobox_dropdown(event.input, &dropdowns) else {
+            continue;
+        };
+        *filtered = filter_items(items, &event.value, combobox.mode);
+        highlighted.set_if_neq(HighlightedItem(filtered.0.first().map(|(i, _)| *i)));
+        if filtered.0.is_empty() {
+            popups.close(dropdown);
+        } else if !popups.is_open(dropdown) {
+            popups.open(dropdown);
+        }
+    }
+}
+
+/// Shows the selected label in searchable comboboxes when it changes, and again
+/// once the player leaves one without picking anything.
+#[allow(clippy::type_complexity)]
+fn sync_searchable_text(
+    mut combobox_query: Query<
+        (
+            Entity,
+            Ref<SelectedIndex>,
+            &ComboboxItems,
+            &mut TextInput,
+            &mut FilteredItems,
+        ),
+        With<SearchableCombobox>,
+    >,
+    dropdowns: Query<(Entity, &Popup), With<DropdownMenuMenu>>,
+    popups: Popups,
+    focus: Res<InputFocus>,
+) {
+    for (combobox, selected, items, mut input, mut filtered) in &mut combobox_query {
+        let Some(item) = items.get(selected.0) else {
+            continue;
+        };
+        let left = focus.0 != Some(combobox)
+            && combobox_dropdown(combobox, &dropdowns).is_none_or(|d| !popups.is_open(d));
+        if (selected.is_changed() || left) && input.value != item.label {
+            input.set_value(item.label.clone());
+            *filtered = FilteredItems::all(items.len());
+        }
+    }
+}
+
+fn matched_text_color(highlighted: bool, ui_style: &UiStyle) -> Color {
+    if highlighted {
+        ui_style.detail_colors.matched_highlighted_text
+    } else {
+        ui_style.detail_colors.matched_text
+    }
+}
+
+/// Hides the items that don't match and splits the labels of the others
+/// into spans, so the matched characters stand out.
+#[allow(clippy::type_complexity)]
+fn update_filtered_items(
+    mut commands: Commands,
+    combobox_query: Query<(Entity, &FilteredItems, &ComboboxItems), Changed<FilteredItems>>,
+    dropdowns: Query<(Entity, &Popup), With<DropdownMenuMenu>>,
+    children_query: Query<&Children>,
+    mut items: Query<(&DropdownItemIndex, &mut Node, &Children)>,
+    mut texts: Query<(Entity, &mut Text)>,
+) {
+    for (combobox, filtered, labels) in combobox_query {
+        let Some(dropdown) = combobox_dropdown(combobox, &dropdowns) else {
+            continue;
+        };
+        for descendant in children_query.iter_descendants(dropdown) {
+            let Ok((index, mut node, item_children)) = items.get_mut(descendant) else {
+                continue;
+            };
+            let matched = filtered.0.iter().find(|(i, _)| *i == index.0);
+            let display = if matched.is_some() {
+                Display::Flex
+            } else {
+                Display::None
+            };
+            if node.display != display {
+                node.display = display;
+            }
+            let positions = matched.map(|(_, m)| m.as_slice()).unwrap_or_default();
+            let label = &labels[index.0].label;
+            let mut iter = texts.iter_many_mut(item_children);
+            while let Some((text_entity, mut text)) = iter.fetch_next() {
+                let mut entity = commands.entity(text_entity);
+                entity.despawn_related::<Children>();
+                if positions.is_empty() {
+                    text.set_if_neq(Text(label.clone()));
+                    continue;
+                }
+                text.set_if_neq(Text::default());
+                // Runs of characters that are all matched or all unmatched.
+                let mut runs: Vec<(bool, String)> = Vec::new();
+                for (i, c) in label.chars().enumerate() {
+                    let is_match = positions.contains(&i);
+                    match runs.last_mut() {
+                        Some((run_match, run)) if *run_match == is_match => run.push(c),
+                        _ => runs.push((is_match, c.to_string())),
+                    }
+                }
+                entity.with_children(|parent| {
+                    for (is_match, run) in runs {
+                        let mut span = parent.spawn(TextSpan::new(run));
+                        if is_match {
+                            span.insert(MatchedText);
+                        }
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Paints the label spans of dropdown items in the text colour of their item's
+/// [`VisualState`], with the matched characters standing out.
+#[allow(clippy::type_complexity)]
+fn paint_matched_text(
+    ui_style: Res<UiStyle>,
+    items: Query<(&StyleRole, Ref<VisualState>, &Children), With<DropdownItemIndex>>,
+    texts: Query<&Children, With<Text>>,
+    mut spans: Query<(Ref<TextSpan>, &mut TextColor, Has<MatchedText>)>,
+) {
+    for (role, state, children) in items {
+        let restyle = ui_style.is_changed() || state.is_changed();
+        let fore_color = role.colors(&ui_style).colors(*state).fore_color;
+        let matched_color = matched_text_color(*state != VisualState::Normal, &ui_style);
+        for text_children in texts.iter_many(children) {
+            let mut iter = spans.iter_many_mut(text_children);
+            while let Some((span, mut color, matched)) = iter.fetch_next() {
+                if !restyle && !span.is_added() {
+                    continue;
+                }
+                let new_color = if matched { matched_color } else { fore_color };
+                color.set_if_neq(TextColor(new_color));
+            }
+        }
+    }
+}
+
+pub struct SearchableComboboxPlugin;
+
+impl Plugin for SearchableComboboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                filter_searchable_comboboxes.run_if(on_event::<TextInputChanged>),
+                sync_searchable_text,
+                update_filtered_items,
+                paint_matched_text.after(restyle_widgets),
+            )
+                .chain(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn only(indices: &[usize]) -> FilteredItems {
+        FilteredItems(indices.iter().map(|i| (*i, Vec::new())).collect())
+    }
+
+    #[test]
+    fn substring_match() {
+        let mode = FilterMode::Substring;
+        assert_eq!(filter_match("1920x1080", "108", mode), Some(vec![5, 6, 7]));
+        assert_eq!(filter_match("1920x1080", "1x1", mode), None);
+        assert_eq!(filter_match("1920x1080", "", mode), Some(Vec::new()));
+    }
+
+    #[test]
+    fn fuzzy_match() {
+        let mode = FilterMode::Fuzzy;
+        assert_eq!(filter_match("1920x1080", "9x8", mode), Some(vec![1, 4, 7]));
+        assert_eq!(filter_match("1920x1080", "80x", mode), None);
+    }
+
+    #[test]
+    fn matching_ignores_case() {
+        assert_eq!(
+            filter_match("Fullscreen", "SCREEN", FilterMode::Substring),
+            Some(vec![4, 5, 6, 7, 8, 9])
+        );
+        assert_eq!(
+            filter_match("Borderless", "bL", FilterMode::Fuzzy),
+            Some(vec![0, 6])
+        );
+    }
+
+    #[test]
+    fn filter_items_keeps_matching_indices() {
+        let items: Vec<_> = ["1280x720", "1920x1080", "2560x1440"]
+            .into_iter()
+            .map(|label| DropdownMenuItem {
+                label: label.to_string(),
+            })
+            .collect();
+        let filtered = filter_items(&items, "x10", FilterMode::Substring);
+        let indices: Vec<usize> = filtered.0.iter().map(|(i, _)| *i).collect();
+        assert_eq!(indices, vec![1]);
+        assert!(filtered.contains(1));
+        assert!(!filtered.contains(0));
+    }
+
+    #[test]
+    fn highlight_moves_through_matches_and_clamps() {
+        let filtered = only(&[2, 5, 7]);
+        assert_eq!(move_filtered_highlight(&filtered, Some(2), 1), Some(5));
+        assert_eq!(move_filtered_highlight(&filtered, Some(5), -1), Some(2));
+        assert_eq!(move_filtered_highlight(&filtered, Some(7), 1), Some(7));
+        assert_eq!(move_filtered_highlight(&filtered, Some(2), -1), Some(2));
+    }
+
+    #[test]
+    fn highlight_starts_at_either_end() {
+        let filtered = only(&[2, 5, 7]);
+        assert_eq!(move_filtered_highlight(&filtered, None, 1), Some(2));
+        assert_eq!(move_filtered_highlight(&filtered, None, -1), Some(7));
+        // An item that was filtered out counts as no highlight.
+        assert_eq!(move_filtered_highlight(&filtered, Some(3), 1), Some(2));
+        assert_eq!(move_filtered_highlight(&only(&[]), None, 1), None);
+    }
+}
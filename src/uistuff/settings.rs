@@ -1,31 +1,78 @@
 use crate::uistuff::utils::*;
-use bevy::{color::palettes::css::*, ecs::relationship::DescendantIter, prelude::*};
+use bevy::{
+    ecs::relationship::{DescendantIter, RelatedSpawnerCommands},
+    input::{ButtonState, keyboard::KeyboardInput, mouse::MouseScrollUnit},
+    input_focus::FocusedInput,
+    prelude::*,
+    window::PrimaryWindow,
+};
 
 use super::{
-    config::{BackgroundForeground, UiStyle},
-    layouts::{NodeModifier, grid_hor_center_layout, text_box, vertically_centered},
+    audio::{AudioSettings, spawn_audio_settings},
+    checkbox::{CheckboxChanged, Checked, toggle_switch_box},
+    config::{StyleColor, StyleRole, UiStyle, VisualState},
+    input_map::{InputMap, spawn_controls_settings},
+    layouts::{NodeModifier, grid_hor_center_layout, text_box, vertical_stack},
+    popup::{Popup, PopupStack, Popups, place_popup},
+    staging::spawn_page_buttons,
+    tabs::{TabView, spawn_tab_view},
+    theme::{ActiveTheme, DARK_THEME, LIGHT_THEME},
+    video::{VideoSettings, spawn_video_settings},
 };
 
-#[derive(Resource, Clone, Debug, Deref, DerefMut)]
-struct OpenedPopup(Option<Entity>);
-
 #[derive(Component, Clone, Debug, Deref)]
 struct UISelection(String);
 
+/// Index of the currently selected item of a combobox.
+/// Changing it from code updates the label shown on the combobox.
+#[derive(Component, Clone, Copy, Debug, Deref, DerefMut, PartialEq, Eq)]
+pub struct SelectedIndex(pub usize);
+
+#[derive(Component, Clone, Debug, Deref)]
+pub(crate) struct ComboboxItems(pub(crate) Vec<DropdownMenuItem>);
+
+#[derive(Component, Clone, Copy, Debug, Deref)]
+pub struct DropdownItemIndex(pub usize);
+
+/// Sent when the user picks an item from a combobox popup.
+#[derive(Event, Clone, Debug)]
+pub struct ComboboxChanged {
+    pub combobox: Entity,
+    pub index: usize,
+    pub label: String,
+}
+
 #[derive(Component)]
-struct DropdownMenuMenu;
+pub(crate) struct DropdownMenuMenu;
 
 #[derive(Component)]
-struct DropdownMenuButton;
+#[require(Focusable, HighlightedItem, TypeAhead)]
+pub(crate) struct DropdownMenuButton;
 
+/// Item of the open popup that keyboard navigation points at.
+#[derive(Component, Clone, Copy, Debug, Default, Deref, PartialEq, Eq)]
+pub(crate) struct HighlightedItem(pub(crate) Option<usize>);
+
+/// Letters typed recently while the popup is open, used to jump to items by name.
+#[derive(Component, Default, Debug)]
+struct TypeAhead {
+    typed: String,
+    last_input: f32,
+}
+
+/// Pause after which typing starts a new search instead of extending the previous one.
+const TYPE_AHEAD_TIMEOUT: f32 = 1.0;
+
+/// Everything the popup placement depends on, in physical pixels.
 #[derive(Clone, Debug, PartialEq)]
-struct LayoutInfo {
-    position: Vec2,
-    size: Vec2,
+pub(crate) struct LayoutInfo {
+    anchor: Rect,
+    popup_size: Vec2,
+    screen_size: Vec2,
 }
 
 #[derive(Component, Default, Debug, Deref, DerefMut)]
-struct PreviousLayout(Option<LayoutInfo>);
+pub(crate) struct PreviousLayout(Option<LayoutInfo>);
 
 pub struct SettingsPlugin;
 
@@ -34,197 +81,835 @@ pub struct DropdownMenuItem {
     pub label: String,
 }
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Debug)]
 pub struct DropdownMenu {
     pub items: Vec<DropdownMenuItem>,
+    /// Longer menus scroll instead of growing past this many items.
+    pub max_visible_items: usize,
+}
+
+impl Default for DropdownMenu {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            max_visible_items: 8,
+        }
+    }
 }
 
+/// The scrolling part of a dropdown menu, holding its items.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct DropdownList {
+    max_visible_items: usize,
+}
+
+#[derive(Component)]
+struct DropdownScrollbar;
+
+#[derive(Component)]
+struct DropdownScrollThumb;
+
+/// How far one notch of the mouse wheel scrolls a dropdown menu.
+const SCROLL_LINE_HEIGHT: f32 = 30.0;
+
 // menu items
-pub fn menu_item(label: DropdownMenuItem) -> impl Bundle {
+pub fn menu_item(label: DropdownMenuItem, ui_style: &UiStyle) -> impl Bundle {
+    styled_menu_item(label.label, StyleRole::MenuItem, ui_style)
+}
+
+pub fn styled_menu_item(
+    label: impl Into<String>,
+    role: StyleRole,
+    ui_style: &UiStyle,
+) -> impl Bundle {
+    let colors = role.colors(ui_style);
     (
         Node {
             // width: Val::Percent(100.0),
-            padding: UiRect::vertical(Val::Px(5.0)),
+            padding: UiRect::vertical(Val::Px(ui_style.spacing.small)),
             ..Default::default()
         },
-        ChangeColorOnHover {
-            normal_color: BackgroundForeground {
-                back_color: BLUE_VIOLET.into(),
-                fore_color: WHITE.into(),
-            },
-            hover_color: BackgroundForeground {
-                back_color: AQUAMARINE.into(),
-                fore_color: BLACK.into(),
-            },
-        },
-        BackgroundColor(BLUE_VIOLET.into()),
-        children![(Text::new(label.label),)],
+        role,
+        VisualState::Normal,
+        BackgroundColor(colors.normal_colors.back_color),
+        children![(
+            Text::new(label),
+            TextColor(colors.normal_colors.fore_color),
+            TextFont::from_font_size(role.font_size(ui_style)),
+        )],
     )
 }
 
 pub fn dropdown_menu(
-    mut commands: impl GenericSpawner,
+    commands: impl GenericSpawner,
     extra_components: impl Bundle,
     node_modifier: NodeModifier,
     menu: DropdownMenu,
-) {
+    ui_style: &UiStyle,
+) -> Entity {
+    dropdown_menu_frame(
+        commands,
+        extra_components,
+        node_modifier,
+        menu.max_visible_items,
+        ui_style,
+        |parent| {
+            for (idx, item) in menu.items.iter().enumerate() {
+                parent.spawn((menu_item(item.clone(), ui_style), DropdownItemIndex(idx)));
+            }
+        },
+    )
+}
+
+/// Spawns a dropdown menu whose scrolling list is filled by `item_spawner`,
+/// for menus made of more than plain items.
+pub fn dropdown_menu_frame(
+    mut commands: impl GenericSpawner,
+    extra_components: impl Bundle,
+    node_modifier: NodeModifier,
+    max_visible_items: usize,
+    ui_style: &UiStyle,
+    item_spawner: impl FnOnce(&mut RelatedSpawnerCommands<ChildOf>),
+) -> Entity {
     commands
         .generic_spawn((
             extra_components,
             node_modifier.modify(Node {
-                flex_direction: FlexDirection::Column,
+                flex_direction: FlexDirection::Row,
                 align_items: AlignItems::Stretch,
+                border: UiRect::all(Val::Px(ui_style.borders.thin)),
                 ..Default::default()
             }),
-            BackgroundColor(BLUE.into()),
+            StyleColor::MenuBack.background(ui_style),
+            StyleColor::Border.border(ui_style),
+            BorderRadius::all(Val::Px(ui_style.radii.small)),
         ))
         .with_children(|parent| {
-            for item in menu.items.iter() {
-                parent.spawn(menu_item(item.clone()));
+            parent
+                .spawn((
+                    DropdownList { max_visible_items },
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Stretch,
+                        flex_grow: 1.0,
+                        overflow: Overflow::scroll_y(),
+                        ..Default::default()
+                    },
+                ))
+                .with_children(item_spawner)
+                .observe(on_dropdown_scroll);
+            parent
+                .spawn((
+                    DropdownScrollbar,
+                    Node {
+                        width: Val::Px(ui_style.spacing.medium),
+                        ..Default::default()
+                    },
+                    StyleColor::ScrollTrack.background(ui_style),
+                    children![(
+                        DropdownScrollThumb,
+                        Pickable::IGNORE,
+                        Node {
+                            position_type: PositionType::Absolute,
+                            width: Val::Percent(100.0),
+                            ..Default::default()
+                        },
+                        StyleColor::ScrollThumb.background(ui_style),
+                    )],
+                ))
+                .observe(on_scrollbar_pressed)
+                .observe(on_scrollbar_dragged);
+        })
+        .id()
+}
+
+/// How far a scrolling node can scroll, in the units of [`ScrollPosition`].
+fn max_scroll(node: &ComputedNode) -> f32 {
+    ((node.content_size().y - node.size().y) * node.inverse_scale_factor()).max(0.0)
+}
+
+fn on_dropdown_scroll(
+    mut trigger: Trigger<Pointer<Scroll>>,
+    mut lists: Query<(&ComputedNode, &mut ScrollPosition), With<DropdownList>>,
+) {
+    let Ok((node, mut scroll)) = lists.get_mut(trigger.target()) else {
+        return;
+    };
+    let event = trigger.event();
+    let delta = match event.unit {
+        MouseScrollUnit::Line => event.y * SCROLL_LINE_HEIGHT,
+        MouseScrollUnit::Pixel => event.y,
+    };
+    trigger.propagate(false);
+    let offset = (scroll.offset_y - delta).clamp(0.0, max_scroll(node));
+    if scroll.offset_y != offset {
+        scroll.offset_y = offset;
+    }
+}
+
+/// Scrolls the list next to `scrollbar` so the thumb is centered on the pointer.
+fn scroll_to_pointer(
+    scrollbar: Entity,
+    pointer_y: f32,
+    scrollbar_query: &Query<(&GlobalTransform, &ComputedNode, &ChildOf), With<DropdownScrollbar>>,
+    children_query: &Query<&Children>,
+    lists: &mut Query<(&ComputedNode, &mut ScrollPosition), With<DropdownList>>,
+    window: &Query<&Window, With<PrimaryWindow>>,
+) {
+    let Ok((transform, node, child_of)) = scrollbar_query.get(scrollbar) else {
+        return;
+    };
+    let scale_factor = window.single().map_or(1.0, Window::scale_factor);
+    let rect = node_logical_rect(transform, node, scale_factor);
+    if rect.height() <= 0.0 {
+        return;
+    }
+    let fraction = ((pointer_y - rect.min.y) / rect.height()).clamp(0.0, 1.0);
+    let Ok(siblings) = children_query.get(child_of.parent()) else {
+        return;
+    };
+    let mut iter = lists.iter_many_mut(siblings);
+    while let Some((list_node, mut scroll)) = iter.fetch_next() {
+        let scale = list_node.inverse_scale_factor();
+        let content = list_node.content_size().y * scale;
+        let visible = list_node.size().y * scale;
+        scroll.offset_y = (fraction * content - visible / 2.0).clamp(0.0, max_scroll(list_node));
+    }
+}
+
+fn on_scrollbar_pressed(
+    trigger: Trigger<Pointer<Pressed>>,
+    scrollbar_query: Query<(&GlobalTransform, &ComputedNode, &ChildOf), With<DropdownScrollbar>>,
+    children_query: Query<&Children>,
+    mut lists: Query<(&ComputedNode, &mut ScrollPosition), With<DropdownList>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+) {
+    if trigger.event().button != PointerButton::Primary {
+        return;
+    }
+    scroll_to_pointer(
+        trigger.target(),
+        trigger.event().pointer_location.position.y,
+        &scrollbar_query,
+        &children_query,
+        &mut lists,
+        &window,
+    );
+}
+
+fn on_scrollbar_dragged(
+    trigger: Trigger<Pointer<Drag>>,
+    scrollbar_query: Query<(&GlobalTransform, &ComputedNode, &ChildOf), With<DropdownScrollbar>>,
+    children_query: Query<&Children>,
+    mut lists: Query<(&ComputedNode, &mut ScrollPosition), With<DropdownList>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+) {
+    if trigger.event().button != PointerButton::Primary {
+        return;
+    }
+    scroll_to_pointer(
+        trigger.target(),
+        trigger.event().pointer_location.position.y,
+        &scrollbar_query,
+        &children_query,
+        &mut lists,
+        &window,
+    );
+}
+
+/// Sizes the scrollbar thumbs to the visible part of their lists,
+/// hiding the scrollbar when everything fits.
+fn update_dropdown_scrollbars(
+    lists: Query<(&ComputedNode, &ScrollPosition, &ChildOf), With<DropdownList>>,
+    children_query: Query<&Children>,
+    mut scrollbars: Query<&mut Node, (With<DropdownScrollbar>, Without<DropdownScrollThumb>)>,
+    mut thumbs: Query<&mut Node, (With<DropdownScrollThumb>, Without<DropdownScrollbar>)>,
+) {
+    for (list_node, scroll, child_of) in lists {
+        let content = list_node.content_size().y * list_node.inverse_scale_factor();
+        let visible = list_node.size().y * list_node.inverse_scale_factor();
+        let display = if content > visible + 0.5 {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        for sibling in children_query.iter_descendants(child_of.parent()) {
+            if let Ok(mut scrollbar) = scrollbars.get_mut(sibling)
+                && scrollbar.display != display
+            {
+                scrollbar.display = display;
+            }
+            if let Ok(mut thumb) = thumbs.get_mut(sibling)
+                && content > 0.0
+            {
+                let top = Val::Percent(scroll.offset_y / content * 100.0);
+                let height = Val::Percent(visible / content * 100.0);
+                if thumb.top != top || thumb.height != height {
+                    thumb.top = top;
+                    thumb.height = height;
+                }
             }
-        });
+        }
+    }
 }
 
+/// Spawns a combobox showing `menu.items[selected]`. An out of range `selected`
+/// selects the last item, and an empty menu shows no label.
 pub fn spawn_combobox(
     mut commands: impl GenericSpawner,
+    extra_components: impl Bundle,
     menu: DropdownMenu,
+    selected: usize,
     node_modifier: NodeModifier,
-    ui_assets: Res<UIAssets>,
-    ui_style: Res<UiStyle>,
-) {
-    let first_item = menu.items[0].label.clone();
+    ui_assets: &UIAssets,
+    ui_style: &UiStyle,
+) -> Entity {
+    let selected = selected.min(menu.items.len().saturating_sub(1));
+    let selected_item = menu
+        .items
+        .get(selected)
+        .map(|item| item.label.clone())
+        .unwrap_or_default();
     commands
         .generic_spawn((
+            extra_components,
             text_box(
-                first_item.clone(),
+                selected_item.clone(),
                 ui_assets
                     .font
                     .clone()
                     .expect("Font should be loaded already"),
                 node_modifier,
-                ui_style.button_style.normal_colors,
+                StyleRole::Normal,
+                ui_style,
             ),
             DropdownMenuButton,
-            UISelection(first_item),
+            UISelection(selected_item),
+            SelectedIndex(selected),
+            ComboboxItems(menu.items.clone()),
             Name::new("Combobox"),
             PreviousLayout(None),
         ))
         .with_children(|parent| {
-            dropdown_menu(
-                parent,
-                (DropdownMenuMenu, Name::new("Dropdown"), GlobalZIndex(100)),
+            let owner = parent.target_entity();
+            let dropdown = dropdown_menu(
+                &mut *parent,
+                (DropdownMenuMenu, Name::new("Dropdown"), Popup { owner }),
                 NodeModifier::new().force_absolute_pos().spawn_hidden(),
                 menu,
+                ui_style,
             );
+            parent
+                .commands()
+                .entity(dropdown)
+                .observe(on_dropdown_item_clicked)
+                .observe(on_dropdown_item_hovered);
         })
-        .observe(on_combobutton_clicked);
+        .observe(on_combobutton_clicked)
+        .observe(on_combobox_key)
+        .id()
+}
+
+/// Index of the dropdown item `target` belongs to, if any.
+fn dropdown_item_at(
+    target: Entity,
+    index_query: &Query<&DropdownItemIndex>,
+    parent_query: &Query<&ChildOf>,
+) -> Option<usize> {
+    std::iter::once(target)
+        .chain(parent_query.iter_ancestors(target))
+        .find_map(|e| index_query.get(e).ok())
+        .map(|index| index.0)
+}
+
+pub(crate) fn select_combobox_item(
+    combobox: Entity,
+    index: usize,
+    selected: &mut Mut<SelectedIndex>,
+    items: &ComboboxItems,
+    changed: &mut EventWriter<ComboboxChanged>,
+) {
+    selected.set_if_neq(SelectedIndex(index));
+    changed.write(ComboboxChanged {
+        combobox,
+        index,
+        label: items[index].label.clone(),
+    });
 }
 
-fn on_combobutton_clicked(
+pub(crate) fn on_dropdown_item_clicked(
     mut trigger: Trigger<Pointer<Click>>,
-    mut child_query: Query<&mut Node, With<DropdownMenuMenu>>,
+    index_query: Query<&DropdownItemIndex>,
+    parent_query: Query<&ChildOf>,
+    mut combobox_query: Query<(&mut SelectedIndex, &ComboboxItems)>,
+    mut popups: Popups,
+    mut changed: EventWriter<ComboboxChanged>,
+) {
+    let dropdown = trigger.target();
+    let Some(index) = dropdown_item_at(trigger.event().target, &index_query, &parent_query) else {
+        return;
+    };
+    trigger.propagate(false);
+    let Some(combobox) = popups.owner(dropdown) else {
+        return;
+    };
+    let Ok((mut selected, items)) = combobox_query.get_mut(combobox) else {
+        return;
+    };
+    popups.close(dropdown);
+    select_combobox_item(combobox, index, &mut selected, items, &mut changed);
+}
+
+/// Keeps the keyboard highlight on the item under the pointer.
+pub(crate) fn on_dropdown_item_hovered(
+    trigger: Trigger<Pointer<Over>>,
+    index_query: Query<&DropdownItemIndex>,
+    parent_query: Query<&ChildOf>,
+    popup_query: Query<&Popup>,
+    mut highlight_query: Query<&mut HighlightedItem>,
+) {
+    let Some(index) = dropdown_item_at(trigger.event().target, &index_query, &parent_query) else {
+        return;
+    };
+    let Ok(combobox) = popup_query.get(trigger.target()).map(|popup| popup.owner) else {
+        return;
+    };
+    if let Ok(mut highlighted) = highlight_query.get_mut(combobox) {
+        highlighted.set_if_neq(HighlightedItem(Some(index)));
+    }
+}
+
+/// First item whose label starts with `typed`, ignoring case, searching from `current`.
+/// Typing the same letter repeatedly cycles through the items starting with it.
+fn type_ahead_match(items: &[DropdownMenuItem], typed: &str, current: usize) -> Option<usize> {
+    let typed = typed.to_lowercase();
+    let mut chars = typed.chars();
+    let first = chars.next()?;
+    let (prefix, start) = if chars.all(|c| c == first) {
+        (first.to_string(), current + 1)
+    } else {
+        (typed.clone(), current)
+    };
+    (0..items.len())
+        .map(|i| (start + i) % items.len())
+        .find(|&i| items[i].label.to_lowercase().starts_with(&prefix))
+}
+
+/// Enter, Space or Down open the popup. While it's open, Up/Down/Home/End and typed
+/// letters move the highlight and Enter picks the highlighted item.
+fn on_combobox_key(
+    mut trigger: Trigger<FocusedInput<KeyboardInput>>,
+    mut combobox_query: Query<(
+        &mut SelectedIndex,
+        &mut HighlightedItem,
+        &mut TypeAhead,
+        &ComboboxItems,
+        &Children,
+    )>,
+    dropdown_query: Query<(), With<DropdownMenuMenu>>,
+    mut popups: Popups,
+    mut changed: EventWriter<ComboboxChanged>,
+    time: Res<Time>,
+) {
+    let input = &trigger.event().input;
+    if input.state != ButtonState::Pressed {
+        return;
+    }
+    let key_code = input.key_code;
+    let text = input.text.clone();
+    let combobox = trigger.target();
+    let Ok((mut selected, mut highlighted, mut type_ahead, items, children)) =
+        combobox_query.get_mut(combobox)
+    else {
+        return;
+    };
+    let Some(dropdown) = children.iter().find(|c| dropdown_query.contains(*c)) else {
+        return;
+    };
+    if !popups.is_open(dropdown) {
+        if matches!(
+            key_code,
+            KeyCode::Enter | KeyCode::NumpadEnter | KeyCode::Space | KeyCode::ArrowDown
+        ) {
+            trigger.propagate(false);
+            popups.open(dropdown);
+            highlighted.set_if_neq(HighlightedItem(Some(selected.0)));
+        }
+        return;
+    }
+    trigger.propagate(false);
+    let current = highlighted.unwrap_or(selected.0);
+    let last = items.len().saturating_sub(1);
+    let next = match key_code {
+        KeyCode::ArrowUp => current.saturating_sub(1),
+        KeyCode::ArrowDown => (current + 1).min(last),
+        KeyCode::Home => 0,
+        KeyCode::End => last,
+        KeyCode::Enter | KeyCode::NumpadEnter => {
+            popups.close(dropdown);
+            select_combobox_item(combobox, current, &mut selected, items, &mut changed);
+            return;
+        }
+        _ => {
+            let Some(text) = text.filter(|t| !t.chars().any(char::is_control)) else {
+                return;
+            };
+            let now = time.elapsed_secs();
+            if now - type_ahead.last_input > TYPE_AHEAD_TIMEOUT {
+                type_ahead.typed.clear();
+            }
+            type_ahead.last_input = now;
+            type_ahead.typed.push_str(&text.to_lowercase());
+            let Some(index) = type_ahead_match(items, &type_ahead.typed, current) else {
+                return;
+            };
+            index
+        }
+    };
+    highlighted.set_if_neq(HighlightedItem(Some(next)));
+}
+
+/// Marks the highlighted item of each combobox popup [`Selected`],
+/// so it's painted in the selected colours of its role.
+fn mark_highlighted_items(
+    mut commands: Commands,
+    dropdowns: Query<(Entity, &Popup), With<DropdownMenuMenu>>,
+    highlights: Query<&HighlightedItem>,
+    children_query: Query<&Children>,
+    items: Query<(&DropdownItemIndex, Has<Selected>)>,
+) {
+    for (dropdown, popup) in dropdowns {
+        let Ok(highlighted) = highlights.get(popup.owner) else {
+            continue;
+        };
+        for descendant in children_query.iter_descendants(dropdown) {
+            let Ok((index, selected)) = items.get(descendant) else {
+                continue;
+            };
+            let is_highlighted = highlighted.0 == Some(index.0);
+            if is_highlighted && !selected {
+                commands.entity(descendant).insert(Selected);
+            } else if !is_highlighted && selected {
+                commands.entity(descendant).remove::<Selected>();
+            }
+        }
+    }
+}
+
+/// Scrolls open combobox popups so the highlighted item is visible,
+/// both when the popup opens and while moving through the items.
+fn scroll_to_highlighted_item(
+    dropdowns: Query<(Entity, &Popup), With<DropdownMenuMenu>>,
+    highlights: Query<Ref<HighlightedItem>>,
+    children_query: Query<&Children>,
+    mut lists: Query<(Ref<ComputedNode>, &mut ScrollPosition, &Children), With<DropdownList>>,
+    items: Query<&ComputedNode, With<DropdownItemIndex>>,
+    stack: Res<PopupStack>,
+) {
+    for (dropdown, popup) in dropdowns {
+        if !stack.contains(&dropdown) {
+            continue;
+        }
+        let Ok(highlighted) = highlights.get(popup.owner) else {
+            continue;
+        };
+        let Some(index) = highlighted.0 else {
+            continue;
+        };
+        for list in children_query.iter_descendants(dropdown) {
+            let Ok((list_node, mut scroll, list_children)) = lists.get_mut(list) else {
+                continue;
+            };
+            if !highlighted.is_changed() && !list_node.is_changed() {
+                continue;
+            }
+            let scale = list_node.inverse_scale_factor();
+            // Items hidden by a filter have no height, so they don't count.
+            let heights: Vec<f32> = items
+                .iter_many(list_children)
+                .map(|item| item.size().y * scale)
+                .collect();
+            let top: f32 = heights.iter().take(index).sum();
+            let bottom = top + heights.get(index).copied().unwrap_or(0.0);
+            let visible = list_node.size().y * scale;
+            if top < scroll.offset_y {
+                scroll.offset_y = top;
+            } else if bottom > scroll.offset_y + visible {
+                scroll.offset_y = bottom - visible;
+            }
+        }
+    }
+}
+
+fn on_combobox_selection_changed(
+    query: Query<
+        (&SelectedIndex, &ComboboxItems, &mut UISelection, &Children),
+        Changed<SelectedIndex>,
+    >,
+    mut text_query: Query<&mut Text>,
+) {
+    for (selected, items, mut selection, children) in query {
+        let Some(item) = items.get(selected.0) else {
+            warn!("Combobox selection {} is out of range", selected.0);
+            continue;
+        };
+        selection.0 = item.label.clone();
+        set_box_text(children, &mut text_query, item.label.clone());
+    }
+}
+
+pub(crate) fn on_combobutton_clicked(
+    mut trigger: Trigger<Pointer<Click>>,
+    child_query: Query<(), With<DropdownMenuMenu>>,
     children_query: Query<&Children, With<DropdownMenuButton>>,
-    mut opened_popup: ResMut<OpenedPopup>,
+    mut highlight_query: Query<(&SelectedIndex, &mut HighlightedItem)>,
+    mut popups: Popups,
 ) {
     let target = trigger.target();
     for child in DescendantIter::new(&children_query, target) {
-        if let Ok(mut my_node) = child_query.get_mut(child) {
-            my_node.display = Display::Flex;
-            opened_popup.0 = Some(child);
+        if child_query.contains(child) {
+            popups.toggle(child);
             trigger.propagate(false);
         }
     }
+    if let Ok((selected, mut highlighted)) = highlight_query.get_mut(target) {
+        highlighted.set_if_neq(HighlightedItem(Some(selected.0)));
+    }
 }
 
-//creates combobox with an associated menu
+/// Keeps open combobox popups inside the window, following the combobox when it
+/// moves and the window when it's resized.
 #[allow(clippy::type_complexity)]
 fn on_combobutton_layout(
-    query: Query<
-        (&ComputedNode, &Transform, &mut PreviousLayout, &Children),
-        (With<DropdownMenuButton>, Changed<ComputedNode>),
+    mut owners: Query<(
+        &ComputedNode,
+        &GlobalTransform,
+        &ComputedNodeTarget,
+        &mut PreviousLayout,
+    )>,
+    mut drop_query: Query<
+        (
+            &Popup,
+            Option<&ChildOf>,
+            &mut Node,
+            &ComputedNode,
+            &Children,
+        ),
+        With<DropdownMenuMenu>,
     >,
-    mut drop_query: Query<&mut Node, With<DropdownMenuMenu>>,
+    mut lists: Query<(&DropdownList, &mut Node, &Children), Without<DropdownMenuMenu>>,
+    items: Query<&ComputedNode, With<DropdownItemIndex>>,
 ) {
-    for (cn, tr, mut pl, children) in query {
-        let plane_translation = tr.translation.xy();
-        if let Some(ref li) = pl.0 {
-            if li.position == plane_translation && li.size == cn.size {
-                continue;
-            }
+    for (popup, child_of, mut node, popup_node, popup_children) in &mut drop_query {
+        if node.display == Display::None {
+            continue;
         }
-        info!("computed node location changed");
+        let Ok((cn, tr, target, mut pl)) = owners.get_mut(popup.owner) else {
+            continue;
+        };
+        let mut list_iter = lists.iter_many_mut(popup_children);
+        let Some((list, mut list_node, list_children)) = list_iter.fetch_next() else {
+            continue;
+        };
+        // The height the popup would have without being squeezed to fit on screen.
+        // Items hidden by a filter have no height and don't count.
+        let visible_height = items
+            .iter_many(list_children)
+            .map(|item| item.size().y)
+            .filter(|height| *height > 0.0)
+            .take(list.max_visible_items)
+            .sum();
         let li = LayoutInfo {
-            position: plane_translation,
-            size: cn.size(),
+            anchor: Rect::from_center_size(tr.translation().truncate(), cn.size()),
+            popup_size: Vec2::new(popup_node.size().x, visible_height),
+            screen_size: target.physical_size().as_vec2(),
         };
-        for child in children {
-            if let Ok(mut ch) = drop_query.get_mut(*child) {
-                ch.top = Val::Px(li.size.y);
-                ch.left = Val::Px(0.0);
-            }
+        if pl.0.as_ref() == Some(&li) {
+            continue;
         }
+        let placement = place_popup(li.anchor, li.popup_size, li.screen_size);
+        // Popups inside their combobox are positioned from the inside of its border,
+        // the others from the window.
+        let origin = if child_of.is_some_and(|c| c.parent() == popup.owner) {
+            let border = cn.border();
+            -Vec2::new(border.left, border.top)
+        } else {
+            li.anchor.min
+        };
+        let scale = cn.inverse_scale_factor();
+        node.left = Val::Px((origin.x + placement.offset.x) * scale);
+        node.top = Val::Px((origin.y + placement.offset.y) * scale);
+        let height = placement.max_height.unwrap_or(visible_height);
+        list_node.max_height = Val::Px(height * scale);
         pl.0 = Some(li);
     }
 }
 
-fn on_outside_click(
-    _trigger: Trigger<Pointer<Click>>,
-    mut opened_popup: ResMut<OpenedPopup>,
-    mut node_query: Query<&mut Node>,
+/// The switch on the settings page that swaps between the light and dark theme.
+#[derive(Component)]
+struct DarkThemeToggle;
+
+fn on_dark_theme_toggled(
+    mut events: EventReader<CheckboxChanged>,
+    toggles: Query<(), With<DarkThemeToggle>>,
+    mut theme: ResMut<ActiveTheme>,
+    asset_server: Res<AssetServer>,
 ) {
-    info!("Outside click detected!");
-    if let Some(popup) = opened_popup.take() {
-        let mut node = node_query
-            .get_mut(popup)
-            .expect("UI nodes should contain Node");
-        node.display = Display::None;
+    for event in events.read() {
+        if !toggles.contains(event.checkbox) {
+            continue;
+        }
+        let name = if event.checked.is_on() {
+            DARK_THEME
+        } else {
+            LIGHT_THEME
+        };
+        theme.switch(name, &asset_server);
     }
 }
 
-pub fn spawn_settings(mut commands: Commands, ui_assets: Res<UIAssets>, ui_style: Res<UiStyle>) {
-    let root_entity = vertically_centered(
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_settings(
+    mut commands: Commands,
+    ui_assets: Res<UIAssets>,
+    ui_style: Res<UiStyle>,
+    active_theme: Res<ActiveTheme>,
+    video_settings: Res<VideoSettings>,
+    audio_settings: Res<AudioSettings>,
+    input_map: Res<InputMap>,
+) {
+    // Stacked from the top, so the tab headers stay put when pages differ in height.
+    vertical_stack(
         &mut commands,
         (
-            BackgroundColor(BLANCHED_ALMOND.into()),
+            StyleColor::Background.background(&ui_style),
             Name::new("VertLayout"),
         ),
         NodeModifier::root(),
         |parent| {
-            grid_hor_center_layout(parent, (), NodeModifier::new(), 2, |parent| {
-                spawn_combobox(
-                    &mut *parent,
-                    DropdownMenu {
-                        items: vec![
-                            DropdownMenuItem {
-                                label: "position 1".to_string(),
-                            },
-                            DropdownMenuItem {
-                                label: "position 2".to_string(),
-                            },
-                            DropdownMenuItem {
-                                label: "position 3".to_string(),
-                            },
-                        ],
-                    },
-                    NodeModifier::new().set_grid_column(GridPlacement::start_span(2, 1)),
-                    ui_assets,
-                    ui_style,
-                );
-                parent.spawn((Text("Hello world\nIn this uncertain times,\nthe world becomes more dangerous than before".to_string()), 
-                    Node {
-                        grid_column: GridPlacement::start_span(2, 1),
-                        ..Default::default()
-                    }));
+            spawn_tab_view(
+                &mut *parent,
+                (),
+                TabView::new(["Video", "Audio", "Controls"], 0),
+                NodeModifier::new(),
+                &ui_assets,
+                &ui_style,
+                |index, parent| {
+                    grid_hor_center_layout(
+                        parent,
+                        (),
+                        NodeModifier::new(),
+                        3,
+                        |parent| match index {
+                            0 => spawn_video_settings(
+                                parent,
+                                &ui_assets,
+                                &ui_style,
+                                &video_settings,
+                                1,
+                            ),
+                            1 => spawn_audio_settings(
+                                parent,
+                                &ui_assets,
+                                &ui_style,
+                                &audio_settings,
+                                1,
+                            ),
+                            _ => spawn_controls_settings(
+                                parent, &ui_assets, &ui_style, &input_map, 1,
+                            ),
+                        },
+                    );
+                },
+            );
+            grid_hor_center_layout(parent, (), NodeModifier::new(), 3, |parent| {
+                spawn_page_buttons(parent, &ui_assets, &ui_style, 1);
+                parent.spawn((
+                    toggle_switch_box(
+                        "Dark theme",
+                        ui_assets
+                            .font
+                            .clone()
+                            .expect("Font should be loaded already"),
+                        NodeModifier::new()
+                            .set_grid_column(GridPlacement::start_span(2, 1))
+                            .set_grid_row(GridPlacement::start_span(2, 1)),
+                        Checked::from(active_theme.name == DARK_THEME),
+                        &ui_style,
+                    ),
+                    DarkThemeToggle,
+                ));
             });
         },
     );
-    commands.entity(root_entity).observe(on_outside_click);
 }
 
 impl Plugin for SettingsPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, spawn_settings)
-            .insert_resource(OpenedPopup(None))
-            .add_systems(Update, on_combobutton_layout);
+            .add_event::<ComboboxChanged>()
+            .add_systems(
+                Update,
+                (
+                    on_combobutton_layout,
+                    on_combobox_selection_changed,
+                    mark_highlighted_items.before(update_visual_states),
+                    on_dark_theme_toggled.run_if(on_event::<CheckboxChanged>),
+                    scroll_to_highlighted_item,
+                    update_dropdown_scrollbars,
+                ),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(labels: &[&str]) -> Vec<DropdownMenuItem> {
+        labels
+            .iter()
+            .map(|label| DropdownMenuItem {
+                label: label.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn type_ahead_matches_prefix() {
+        let items = items(&["Windowed", "Borderless", "Fullscreen"]);
+        assert_eq!(type_ahead_match(&items, "bor", 0), Some(1));
+        assert_eq!(type_ahead_match(&items, "fu", 0), Some(2));
+        assert_eq!(type_ahead_match(&items, "ull", 0), None);
+    }
+
+    #[test]
+    fn type_ahead_ignores_case() {
+        let items = items(&["vsync", "Adaptive VSync", "Off"]);
+        assert_eq!(type_ahead_match(&items, "AD", 0), Some(1));
+        assert_eq!(type_ahead_match(&items, "oF", 0), Some(2));
+    }
+
+    #[test]
+    fn repeated_letter_cycles_from_current() {
+        let items = items(&["Low", "Medium", "Large", "High"]);
+        assert_eq!(type_ahead_match(&items, "l", 0), Some(2));
+        assert_eq!(type_ahead_match(&items, "ll", 2), Some(0));
+        assert_eq!(type_ahead_match(&items, "h", 3), Some(3));
+    }
+
+    #[test]
+    fn longer_prefix_can_keep_current() {
+        let items = items(&["Low", "Medium", "Large", "High"]);
+        assert_eq!(type_ahead_match(&items, "lo", 0), Some(0));
+        assert_eq!(type_ahead_match(&items, "la", 3), Some(2));
+    }
+
+    #[test]
+    fn type_ahead_without_match() {
+        let items = items(&["Low", "High"]);
+        assert_eq!(type_ahead_match(&items, "x", 0), None);
+        assert_eq!(type_ahead_match(&items, "", 0), None);
+        assert_eq!(type_ahead_match(&[], "l", 0), None);
     }
 }
@@ -0,0 +1,406 @@
+use crate::uistuff::utils::*;
+use bevy::{
+    input::{ButtonState, gamepad::GamepadButtonChangedEvent, keyboard::KeyboardInput},
+    input_focus::FocusedInput,
+    prelude::*,
+    window::PrimaryWindow,
+};
+
+use super::{
+    config::{StyleColor, StyleRole, UiStyle},
+    layouts::{NodeModifier, text_box},
+};
+
+const TRACK_WIDTH: f32 = 200.0;
+const TRACK_HEIGHT: f32 = 8.0;
+const THUMB_WIDTH: f32 = 14.0;
+const THUMB_HEIGHT: f32 = 24.0;
+/// How many steps PageUp/PageDown move the slider.
+const PAGE_STEPS: f32 = 10.0;
+/// The readout never shows more decimals than this.
+const MAX_DECIMALS: usize = 6;
+
+/// A horizontal slider. `value` is always kept within `min..=max` and snapped to `step`.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+#[require(Focusable)]
+pub struct Slider {
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+    pub value: f32,
+}
+
+impl Slider {
+    /// Panics unless `min <= max`, which also rules out NaN bounds.
+    pub fn new(min: f32, max: f32, step: f32, value: f32) -> Self {
+        assert!(min <= max, "Slider bounds {min}..={max} are invalid");
+        let mut slider = Self {
+            min,
+            max,
+            step,
+            value: min,
+        };
+        slider.value = slider.snap(value);
+        slider
+    }
+
+    /// Clamps `value` into range and rounds it to the nearest step.
+    pub fn snap(&self, value: f32) -> f32 {
+        let value = value.clamp(self.min, self.max);
+        if self.step <= 0.0 {
+            return value;
+        }
+        let snapped = self.min + ((value - self.min) / self.step).round() * self.step;
+        snapped.clamp(self.min, self.max)
+    }
+
+    /// Position of the value along the track, from 0.0 to 1.0.
+    pub fn fraction(&self) -> f32 {
+        if self.max <= self.min {
+            return 0.0;
+        }
+        (self.value - self.min) / (self.max - self.min)
+    }
+
+    fn value_at(&self, fraction: f32) -> f32 {
+        self.snap(self.min + fraction * (self.max - self.min))
+    }
+
+    /// Text shown by the readout, with as many decimals as the step needs:
+    /// the fewest that make `step * 10^decimals` a whole number.
+    pub fn format_value(&self) -> String {
+        let decimals = (0..MAX_DECIMALS)
+            .find(|&decimals| {
+                let scaled = self.step * 10f32.powi(decimals as i32);
+                (scaled - scaled.round()).abs() < 1e-3
+            })
+            .unwrap_or(MAX_DECIMALS);
+        format!("{:.*}", decimals, self.value)
+    }
+}
+
+/// Sent when the user moves a slider.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SliderChanged {
+    pub slider: Entity,
+    pub value: f32,
+}
+
+#[derive(Component)]
+struct SliderTrack;
+
+#[derive(Component)]
+struct SliderFill;
+
+#[derive(Component)]
+struct SliderThumb;
+
+#[derive(Component)]
+struct SliderReadout;
+
+pub fn spawn_slider(
+    mut commands: impl GenericSpawner,
+    extra_components: impl Bundle,
+    slider: Slider,
+    show_readout: bool,
+    node_modifier: NodeModifier,
+    ui_assets: &UIAssets,
+    ui_style: &UiStyle,
+) -> Entity {
+    commands
+        .generic_spawn((
+            extra_components,
+            slider,
+            Name::new("Slider"),
+            node_modifier.modify(Node {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(ui_style.spacing.medium),
+                padding: UiRect::all(Val::Px(ui_style.spacing.small)),
+                ..Default::default()
+            }),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    SliderTrack,
+                    Node {
+                        width: Val::Px(TRACK_WIDTH),
+                        height: Val::Px(TRACK_HEIGHT),
+                        ..Default::default()
+                    },
+                    StyleColor::HoverBack.background(ui_style),
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        SliderFill,
+                        Pickable::IGNORE,
+                        Node {
+                            position_type: PositionType::Absolute,
+                            left: Val::Px(0.0),
+                            height: Val::Percent(100.0),
+                            width: Val::Percent(slider.fraction() * 100.0),
+                            ..Default::default()
+                        },
+                        StyleColor::NormalBack.background(ui_style),
+                    ));
+                    parent.spawn((
+                        SliderThumb,
+                        Pickable::IGNORE,
+                        Node {
+                            position_type: PositionType::Absolute,
+                            left: Val::Percent(slider.fraction() * 100.0),
+                            top: Val::Px((TRACK_HEIGHT - THUMB_HEIGHT) / 2.0),
+                            margin: UiRect::left(Val::Px(-THUMB_WIDTH / 2.0)),
+                            width: Val::Px(THUMB_WIDTH),
+                            height: Val::Px(THUMB_HEIGHT),
+                            ..Default::default()
+                        },
+                        StyleColor::HoverFore.background(ui_style),
+                    ));
+                })
+                .observe(on_track_pressed)
+                .observe(on_track_dragged);
+            if show_readout {
+                parent.spawn((
+                    SliderReadout,
+                    text_box(
+                        slider.format_value(),
+                        ui_assets
+                            .font
+                            .clone()
+                            .expect("Font should be loaded already"),
+                        NodeModifier::new(),
+                        StyleRole::Normal,
+                        ui_style,
+                    ),
+                ));
+            }
+        })
+        .observe(on_slider_key)
+        .observe(on_slider_gamepad_button)
+        .id()
+}
+
+fn set_slider_value(
+    entity: Entity,
+    slider: &mut Slider,
+    value: f32,
+    changed: &mut EventWriter<SliderChanged>,
+) {
+    let value = slider.snap(value);
+    if value != slider.value {
+        slider.value = value;
+        changed.write(SliderChanged {
+            slider: entity,
+            value,
+        });
+    }
+}
+
+/// Moves the slider so the pointer position along `track` becomes its value.
+fn move_to_pointer(
+    track: Entity,
+    pointer_x: f32,
+    track_query: &Query<(&GlobalTransform, &ComputedNode, &ChildOf), With<SliderTrack>>,
+    sliders: &mut Query<&mut Slider>,
+    window: &Query<&Window, With<PrimaryWindow>>,
+    changed: &mut EventWriter<SliderChanged>,
+) {
+    let Ok((transform, node, child_of)) = track_query.get(track) else {
+        return;
+    };
+    let scale_factor = window.single().map_or(1.0, Window::scale_factor);
+    let rect = node_logical_rect(transform, node, scale_factor);
+    if rect.width() <= 0.0 {
+        return;
+    }
+    let fraction = ((pointer_x - rect.min.x) / rect.width()).clamp(0.0, 1.0);
+    let slider_entity = child_of.parent();
+    if let Ok(mut slider) = sliders.get_mut(slider_entity) {
+        let value = slider.value_at(fraction);
+        set_slider_value(slider_entity, &mut slider, value, changed);
+    }
+}
+
+fn on_track_pressed(
+    trigger: Trigger<Pointer<Pressed>>,
+    track_query: Query<(&GlobalTransform, &ComputedNode, &ChildOf), With<SliderTrack>>,
+    mut sliders: Query<&mut Slider>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut changed: EventWriter<SliderChanged>,
+) {
+    if trigger.event().button != PointerButton::Primary {
+        return;
+    }
+    move_to_pointer(
+        trigger.target(),
+        trigger.event().pointer_location.position.x,
+        &track_query,
+        &mut sliders,
+        &window,
+        &mut changed,
+    );
+}
+
+fn on_track_dragged(
+    trigger: Trigger<Pointer<Drag>>,
+    track_query: Query<(&GlobalTransform, &ComputedNode, &ChildOf), With<SliderTrack>>,
+    mut sliders: Query<&mut Slider>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut changed: EventWriter<SliderChanged>,
+) {
+    if trigger.event().button != PointerButton::Primary {
+        return;
+    }
+    move_to_pointer(
+        trigger.target(),
+        trigger.event().pointer_location.position.x,
+        &track_query,
+        &mut sliders,
+        &window,
+        &mut changed,
+    );
+}
+
+fn on_slider_key(
+    mut trigger: Trigger<FocusedInput<KeyboardInput>>,
+    mut sliders: Query<&mut Slider>,
+    mut changed: EventWriter<SliderChanged>,
+) {
+    let input = &trigger.event().input;
+    if input.state != ButtonState::Pressed {
+        return;
+    }
+    let entity = trigger.target();
+    let Ok(mut slider) = sliders.get_mut(entity) else {
+        return;
+    };
+    let value = match input.key_code {
+        KeyCode::ArrowLeft | KeyCode::ArrowDown => slider.value - slider.step,
+        KeyCode::ArrowRight | KeyCode::ArrowUp => slider.value + slider.step,
+        KeyCode::PageDown => slider.value - slider.step * PAGE_STEPS,
+        KeyCode::PageUp => slider.value + slider.step * PAGE_STEPS,
+        KeyCode::Home => slider.min,
+        KeyCode::End => slider.max,
+        _ => return,
+    };
+    trigger.propagate(false);
+    set_slider_value(entity, &mut slider, value, &mut changed);
+}
+
+fn on_slider_gamepad_button(
+    mut trigger: Trigger<FocusedInput<GamepadButtonChangedEvent>>,
+    mut sliders: Query<&mut Slider>,
+    mut changed: EventWriter<SliderChanged>,
+) {
+    let input = &trigger.event().input;
+    if input.state != ButtonState::Pressed {
+        return;
+    }
+    let entity = trigger.target();
+    let Ok(mut slider) = sliders.get_mut(entity) else {
+        return;
+    };
+    let value = match input.button {
+        GamepadButton::DPadLeft => slider.value - slider.step,
+        GamepadButton::DPadRight => slider.value + slider.step,
+        _ => return,
+    };
+    trigger.propagate(false);
+    set_slider_value(entity, &mut slider, value, &mut changed);
+}
+
+fn update_slider_visuals(
+    sliders: Query<(&Slider, &Children), Changed<Slider>>,
+    children_query: Query<&Children>,
+    mut fill_query: Query<&mut Node, (With<SliderFill>, Without<SliderThumb>)>,
+    mut thumb_query: Query<&mut Node, (With<SliderThumb>, Without<SliderFill>)>,
+    readout_query: Query<&Children, With<SliderReadout>>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (slider, children) in sliders {
+        let percent = Val::Percent(slider.fraction() * 100.0);
+        for child in children {
+            if let Ok(readout_children) = readout_query.get(*child) {
+                set_box_text(readout_children, &mut text_query, slider.format_value());
+            }
+            for grandchild in children_query.get(*child).into_iter().flatten() {
+                if let Ok(mut fill) = fill_query.get_mut(*grandchild) {
+                    fill.width = percent;
+                }
+                if let Ok(mut thumb) = thumb_query.get_mut(*grandchild) {
+                    thumb.left = percent;
+                }
+            }
+        }
+    }
+}
+
+pub struct SliderPlugin;
+
+impl Plugin for SliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SliderChanged>()
+            .add_systems(Update, update_slider_visuals);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snap_clamps_and_rounds_to_steps() {
+        let slider = Slider::new(0.0, 100.0, 5.0, 0.0);
+        assert_eq!(slider.snap(-10.0), 0.0);
+        assert_eq!(slider.snap(101.0), 100.0);
+        assert_eq!(slider.snap(12.4), 10.0);
+        assert_eq!(slider.snap(12.6), 15.0);
+    }
+
+    #[test]
+    fn snap_counts_steps_from_min() {
+        let slider = Slider::new(1.0, 2.0, 0.25, 1.0);
+        assert_eq!(slider.snap(1.3), 1.25);
+        assert_eq!(slider.snap(1.9), 2.0);
+    }
+
+    #[test]
+    fn zero_step_only_clamps() {
+        let slider = Slider::new(0.0, 1.0, 0.0, 0.0);
+        assert_eq!(slider.snap(0.123), 0.123);
+        assert_eq!(slider.snap(2.0), 1.0);
+    }
+
+    #[test]
+    fn value_at_maps_track_fraction_to_value() {
+        let slider = Slider::new(-50.0, 50.0, 10.0, 0.0);
+        assert_eq!(slider.value_at(0.0), -50.0);
+        assert_eq!(slider.value_at(1.0), 50.0);
+        assert_eq!(slider.value_at(0.5), 0.0);
+        assert_eq!(slider.value_at(0.66), 20.0);
+        assert_eq!(slider.value_at(1.5), 50.0);
+    }
+
+    #[test]
+    fn format_value_shows_the_decimals_of_the_step() {
+        assert_eq!(Slider::new(0.0, 100.0, 5.0, 35.0).format_value(), "35");
+        assert_eq!(Slider::new(0.0, 1.0, 0.25, 0.25).format_value(), "0.25");
+        assert_eq!(Slider::new(0.0, 1.0, 0.1, 0.3).format_value(), "0.3");
+        assert_eq!(Slider::new(0.0, 1.0, 0.05, 0.15).format_value(), "0.15");
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_rejects_inverted_bounds() {
+        Slider::new(1.0, 0.0, 0.1, 0.5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_rejects_nan_bounds() {
+        Slider::new(f32::NAN, 1.0, 0.1, 0.5);
+    }
+}
@@ -0,0 +1,430 @@
+use crate::uistuff::utils::*;
+use bevy::{input_focus::InputFocus, prelude::*};
+
+use super::{
+    config::UiStyle,
+    layouts::{NodeModifier, button_box},
+    text_input::{CharFilter, TextInput, TextInputSubmitted, text_input_box},
+};
+
+/// Delay before a held -/+ button starts repeating.
+const REPEAT_DELAY: f32 = 0.4;
+/// Repeat interval right after the delay, shrinking towards [`FASTEST_REPEAT`] while held.
+const SLOWEST_REPEAT: f32 = 0.15;
+const FASTEST_REPEAT: f32 = 0.02;
+/// How long a button has to be held to reach the fastest repeat rate.
+const ACCELERATION_TIME: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpinMode {
+    Integer,
+    Float { decimals: usize },
+}
+
+/// A numeric value with -/+ buttons and a field for typing it in.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct SpinBox {
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+    pub value: f32,
+    pub mode: SpinMode,
+}
+
+impl SpinBox {
+    pub fn integer(min: i32, max: i32, value: i32) -> Self {
+        Self {
+            min: min as f32,
+            max: max as f32,
+            step: 1.0,
+            value: value as f32,
+            mode: SpinMode::Integer,
+        }
+    }
+
+    pub fn float(min: f32, max: f32, step: f32, decimals: usize, value: f32) -> Self {
+        Self {
+            min,
+            max,
+            step,
+            value,
+            mode: SpinMode::Float { decimals },
+        }
+    }
+
+    pub fn with_step(mut self, step: f32) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// Snaps `value` to the nearest step counted from `min` and clamps it into range.
+    /// Integer mode also rounds it to a whole number.
+    pub fn constrain(&self, value: f32) -> f32 {
+        let value = if self.step > 0.0 {
+            self.min + ((value - self.min) / self.step).round() * self.step
+        } else {
+            value
+        };
+        let value = match self.mode {
+            SpinMode::Integer => value.round(),
+            SpinMode::Float { .. } => value,
+        };
+        value.clamp(self.min, self.max)
+    }
+
+    pub fn format(&self) -> String {
+        match self.mode {
+            SpinMode::Integer => format!("{}", self.value.round() as i64),
+            SpinMode::Float { decimals } => format!("{:.*}", decimals, self.value),
+        }
+    }
+
+    /// Parses typed text, rejecting anything that isn't a number within range.
+    pub fn parse(&self, text: &str) -> Option<f32> {
+        let text = text.trim();
+        let value = match self.mode {
+            SpinMode::Integer => text.parse::<i64>().ok()? as f32,
+            SpinMode::Float { .. } => text.parse::<f32>().ok()?,
+        };
+        (value.is_finite() && value >= self.min && value <= self.max).then_some(value)
+    }
+}
+
+/// Sent when the user changes the value of a [`SpinBox`].
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SpinBoxChanged {
+    pub spin_box: Entity,
+    pub value: f32,
+}
+
+/// The text field of a spin box.
+#[derive(Component)]
+struct SpinBoxField;
+
+/// A -/+ button of a spin box; `held` counts how long it's been pressed
+/// since the press stepped the value.
+#[derive(Component)]
+struct SpinButton {
+    direction: f32,
+    pressed: bool,
+    held: f32,
+    next_repeat: f32,
+}
+
+impl SpinButton {
+    fn new(direction: f32) -> Self {
+        Self {
+            direction,
+            pressed: false,
+            held: 0.0,
+            next_repeat: REPEAT_DELAY,
+        }
+    }
+}
+
+fn field_filter(spin_box: &SpinBox) -> CharFilter {
+    match spin_box.mode {
+        SpinMode::Integer if spin_box.min >= 0.0 => CharFilter::Digits,
+        SpinMode::Integer => CharFilter::Custom(|c| c.is_ascii_digit() || c == '-'),
+        SpinMode::Float { .. } => CharFilter::Decimal,
+    }
+}
+
+pub fn spawn_spin_box(
+    mut commands: impl GenericSpawner,
+    extra_components: impl Bundle,
+    spin_box: SpinBox,
+    node_modifier: NodeModifier,
+    ui_assets: &UIAssets,
+    ui_style: &UiStyle,
+) -> Entity {
+    let font = ui_assets
+        .font
+        .clone()
+        .expect("Font should be loaded already");
+    commands
+        .generic_spawn((
+            extra_components,
+            spin_box,
+            Name::new("SpinBox"),
+            node_modifier.modify(Node {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            }),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                button_box("-", font.clone(), NodeModifier::new(), ui_style),
+                SpinButton::new(-1.0),
+            ));
+            parent.spawn((
+                text_input_box(
+                    TextInput::new(spin_box.format()).with_filter(field_filter(&spin_box)),
+                    font.clone(),
+                    NodeModifier::new(),
+                    ui_style,
+                ),
+                SpinBoxField,
+            ));
+            parent.spawn((
+                button_box("+", font, NodeModifier::new(), ui_style),
+                SpinButton::new(1.0),
+            ));
+        })
+        .observe(on_spin_box_scroll)
+        .id()
+}
+
+fn step_spin_box(
+    entity: Entity,
+    spin_box: &mut SpinBox,
+    steps: f32,
+    changed: &mut EventWriter<SpinBoxChanged>,
+) {
+    set_spin_box_value(
+        entity,
+        spin_box,
+        spin_box.value + steps * spin_box.step,
+        changed,
+    );
+}
+
+fn set_spin_box_value(
+    entity: Entity,
+    spin_box: &mut SpinBox,
+    value: f32,
+    changed: &mut EventWriter<SpinBoxChanged>,
+) {
+    let value = spin_box.constrain(value);
+    if value != spin_box.value {
+        spin_box.value = value;
+        changed.write(SpinBoxChanged {
+            spin_box: entity,
+            value,
+        });
+    }
+}
+
+fn on_spin_box_scroll(
+    mut trigger: Trigger<Pointer<Scroll>>,
+    mut spin_boxes: Query<&mut SpinBox>,
+    mut changed: EventWriter<SpinBoxChanged>,
+) {
+    let entity = trigger.target();
+    let Ok(mut spin_box) = spin_boxes.get_mut(entity) else {
+        return;
+    };
+    let steps = trigger.event().y.signum();
+    if steps != 0.0 {
+        trigger.propagate(false);
+        step_spin_box(entity, &mut spin_box, steps, &mut changed);
+    }
+}
+
+/// Steps once when a -/+ button is pressed, then keeps repeating faster while it's held.
+fn repeat_spin_buttons(
+    time: Res<Time>,
+    buttons: Query<(&mut SpinButton, &Interaction, &ChildOf)>,
+    mut spin_boxes: Query<&mut SpinBox>,
+    mut changed: EventWriter<SpinBoxChanged>,
+) {
+    for (mut button, interaction, child_of) in buttons {
+        if *interaction != Interaction::Pressed {
+            button.pressed = false;
+            button.held = 0.0;
+            button.next_repeat = REPEAT_DELAY;
+            continue;
+        }
+        let Ok(mut spin_box) = spin_boxes.get_mut(child_of.parent()) else {
+            continue;
+        };
+        if !button.pressed {
+            button.pressed = true;
+            step_spin_box(
+                child_of.parent(),
+                &mut spin_box,
+                button.direction,
+                &mut changed,
+            );
+            continue;
+        }
+        button.held += time.delta_secs();
+        while button.held >= button.next_repeat {
+            let acceleration = ((button.held - REPEAT_DELAY) / ACCELERATION_TIME).clamp(0.0, 1.0);
+            button.next_repeat += SLOWEST_REPEAT.lerp(FASTEST_REPEAT, acceleration);
+            step_spin_box(
+                child_of.parent(),
+                &mut spin_box,
+                button.direction,
+                &mut changed,
+            );
+        }
+    }
+}
+
+/// Commits the typed value when Enter is pressed in the field.
+fn on_spin_field_submitted(
+    mut events: EventReader<TextInputSubmitted>,
+    fields: Query<&ChildOf, With<SpinBoxField>>,
+    mut spin_boxes: Query<&mut SpinBox>,
+    mut changed: EventWriter<SpinBoxChanged>,
+) {
+    for event in events.read() {
+        let Ok(child_of) = fields.get(event.input) else {
+            continue;
+        };
+        commit_typed_value(
+            child_of.parent(),
+            &event.value,
+            &mut spin_boxes,
+            &mut changed,
+        );
+    }
+}
+
+/// Commits the typed value when the field loses focus.
+fn on_spin_field_blurred(
+    focus: Res<InputFocus>,
+    mut previous_focus: Local<Option<Entity>>,
+    fields: Query<(&TextInput, &ChildOf), With<SpinBoxField>>,
+    mut spin_boxes: Query<&mut SpinBox>,
+    mut changed: EventWriter<SpinBoxChanged>,
+) {
+    let previous = std::mem::replace(&mut *previous_focus, focus.0);
+    let Some(previous) = previous.filter(|p| focus.0 != Some(*p)) else {
+        return;
+    };
+    if let Ok((input, child_of)) = fields.get(previous) {
+        commit_typed_value(
+            child_of.parent(),
+            &input.value,
+            &mut spin_boxes,
+            &mut changed,
+        );
+    }
+}
+
+/// Invalid text is replaced by the current value again.
+fn commit_typed_value(
+    entity: Entity,
+    text: &str,
+    spin_boxes: &mut Query<&mut SpinBox>,
+    changed: &mut EventWriter<SpinBoxChanged>,
+) {
+    let Ok(mut spin_box) = spin_boxes.get_mut(entity) else {
+        return;
+    };
+    match spin_box.parse(text) {
+        Some(value) => set_spin_box_value(entity, &mut spin_box, value, changed),
+        None => spin_box.set_changed(),
+    }
+}
+
+fn sync_spin_fields(
+    spin_boxes: Query<(&SpinBox, &Children), Changed<SpinBox>>,
+    mut fields: Query<&mut TextInput, With<SpinBoxField>>,
+) {
+    for (spin_box, children) in spin_boxes {
+        let mut iter = fields.iter_many_mut(children);
+        while let Some(mut input) = iter.fetch_next() {
+            let text = spin_box.format();
+            if input.value != text {
+                input.set_value(text);
+            }
+        }
+    }
+}
+
+/// Outlines the field border in red while the typed text isn't a valid value.
+fn show_spin_field_validity(
+    fields: Query<(Ref<TextInput>, &ChildOf, &mut BorderColor), With<SpinBoxField>>,
+    spin_boxes: Query<&SpinBox>,
+    ui_style: Res<UiStyle>,
+) {
+    for (input, child_of, mut border) in fields {
+        if !input.is_changed() && !ui_style.is_changed() {
+            continue;
+        }
+        let Ok(spin_box) = spin_boxes.get(child_of.parent()) else {
+            continue;
+        };
+        let color = if spin_box.parse(&input.value).is_some() {
+            ui_style.button_style.normal_colors.back_color
+        } else {
+            ui_style.detail_colors.invalid_border
+        };
+        border.set_if_neq(BorderColor(color));
+    }
+}
+
+pub struct SpinBoxPlugin;
+
+impl Plugin for SpinBoxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpinBoxChanged>().add_systems(
+            Update,
+            (
+                repeat_spin_buttons,
+                on_spin_field_submitted,
+                on_spin_field_blurred,
+                sync_spin_fields,
+                show_spin_field_validity.after(restyle_widgets),
+            )
+                .chain(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_integer() {
+        let spin_box = SpinBox::integer(-5, 10, 0);
+        assert_eq!(spin_box.parse(" 7 "), Some(7.0));
+        assert_eq!(spin_box.parse("-5"), Some(-5.0));
+        assert_eq!(spin_box.parse("11"), None);
+        assert_eq!(spin_box.parse("2.5"), None);
+        assert_eq!(spin_box.parse(""), None);
+        assert_eq!(spin_box.parse("abc"), None);
+    }
+
+    #[test]
+    fn parse_float() {
+        let spin_box = SpinBox::float(0.0, 1.0, 0.1, 1, 0.5);
+        assert_eq!(spin_box.parse("0.25"), Some(0.25));
+        assert_eq!(spin_box.parse("1"), Some(1.0));
+        assert_eq!(spin_box.parse("-0.1"), None);
+        assert_eq!(spin_box.parse("NaN"), None);
+        assert_eq!(spin_box.parse("inf"), None);
+    }
+
+    #[test]
+    fn constrain_integer() {
+        let spin_box = SpinBox::integer(0, 10, 0);
+        assert_eq!(spin_box.constrain(3.4), 3.0);
+        assert_eq!(spin_box.constrain(-2.0), 0.0);
+        assert_eq!(spin_box.constrain(12.0), 10.0);
+        let spin_box = SpinBox::integer(50, 200, 100).with_step(25.0);
+        assert_eq!(spin_box.constrain(110.0), 100.0);
+        assert_eq!(spin_box.constrain(113.0), 125.0);
+    }
+
+    #[test]
+    fn constrain_float_snaps_to_step() {
+        let spin_box = SpinBox::float(0.5, 2.0, 0.25, 2, 1.0);
+        assert_eq!(spin_box.constrain(1.1), 1.0);
+        assert_eq!(spin_box.constrain(1.2), 1.25);
+        assert_eq!(spin_box.constrain(0.0), 0.5);
+        assert_eq!(spin_box.constrain(5.0), 2.0);
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(SpinBox::integer(0, 10, 4).format(), "4");
+        assert_eq!(SpinBox::float(0.0, 1.0, 0.1, 2, 0.5).format(), "0.50");
+    }
+}
@@ -0,0 +1,396 @@
+use crate::uistuff::utils::*;
+use bevy::{
+    ecs::{relationship::RelatedSpawnerCommands, system::SystemParam},
+    prelude::*,
+};
+
+use super::{
+    audio::AudioSettings,
+    config::{StyleRole, UiStyle},
+    input_map::InputMap,
+    layouts::{NodeModifier, button_box, text_box, vertically_centered},
+    modal::{ModalDialog, ModalResponse, spawn_modal},
+    persistence::SaveSettings,
+    tooltip::Tooltip,
+    video::{VideoSettings, VideoSettingsApplied},
+};
+
+/// Copy of a settings resource that the settings page edits.
+/// The live resource only changes when the page is applied.
+#[derive(Resource, Clone, Debug, Deref, DerefMut, PartialEq)]
+pub struct Staged<T>(pub T);
+
+/// Whether the staged value of a settings entry differs from the live one.
+/// Set on every entity tagged with the entry's field marker.
+#[derive(Component, Clone, Copy, Debug, Default, Deref, PartialEq, Eq)]
+pub struct Modified(pub bool);
+
+/// Sent when the player reverts a single settings entry to its live value.
+/// `entity` carries the field marker of the entry.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RevertField {
+    pub entity: Entity,
+}
+
+/// Where the player goes after leaving the current settings page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingsDestination {
+    Quit,
+}
+
+/// Asks to leave the settings page. With unsaved changes the player first
+/// chooses between applying them, discarding them or staying on the page.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct LeaveSettingsPage(pub SettingsDestination);
+
+/// The label of a settings entry, showing whether the entry was modified.
+#[derive(Component, Clone, Debug)]
+struct SettingLabel(String);
+
+#[derive(Component)]
+struct RevertFieldButton;
+
+#[derive(Resource, Default)]
+struct PendingLeave(Option<SettingsDestination>);
+
+#[derive(Component)]
+struct UnsavedChangesDialog;
+
+/// The live settings together with their staged copies.
+#[derive(SystemParam)]
+pub struct SettingsDraft<'w> {
+    video: ResMut<'w, VideoSettings>,
+    audio: ResMut<'w, AudioSettings>,
+    input: ResMut<'w, InputMap>,
+    staged_video: ResMut<'w, Staged<VideoSettings>>,
+    staged_audio: ResMut<'w, Staged<AudioSettings>>,
+    staged_input: ResMut<'w, Staged<InputMap>>,
+}
+
+impl SettingsDraft<'_> {
+    pub fn is_dirty(&self) -> bool {
+        *self.video != self.staged_video.0
+            || *self.audio != self.staged_audio.0
+            || *self.input != self.staged_input.0
+    }
+
+    /// Copies the staged settings into the live resources.
+    /// Returns the previous video settings if applying changed them.
+    pub fn apply(&mut self) -> Option<VideoSettings> {
+        let previous_video = *self.video;
+        self.video.set_if_neq(self.staged_video.0);
+        self.audio.set_if_neq(self.staged_audio.0);
+        if *self.input != self.staged_input.0 {
+            *self.input = self.staged_input.0.clone();
+        }
+        (previous_video != *self.video).then_some(previous_video)
+    }
+
+    /// Throws the staged changes away.
+    pub fn cancel(&mut self) {
+        self.staged_video.set_if_neq(Staged(*self.video));
+        self.staged_audio.set_if_neq(Staged(*self.audio));
+        if *self.input != self.staged_input.0 {
+            self.staged_input.0 = self.input.clone();
+        }
+    }
+
+    /// Stages the default value of every setting. Nothing changes until applied.
+    pub fn reset_to_defaults(&mut self) {
+        self.staged_video
+            .set_if_neq(Staged(VideoSettings::default()));
+        self.staged_audio
+            .set_if_neq(Staged(AudioSettings::default()));
+        let default_input = InputMap::default();
+        if self.staged_input.0 != default_input {
+            self.staged_input.0 = default_input;
+        }
+    }
+}
+
+fn stage_settings(
+    mut commands: Commands,
+    video: Res<VideoSettings>,
+    audio: Res<AudioSettings>,
+    input: Res<InputMap>,
+) {
+    commands.insert_resource(Staged(*video));
+    commands.insert_resource(Staged(*audio));
+    commands.insert_resource(Staged(input.clone()));
+}
+
+/// Applies the staged settings. Changed video settings have to be confirmed
+/// before they're saved, everything else is saved right away.
+fn apply_staged_settings(
+    draft: &mut SettingsDraft,
+    save: &mut EventWriter<SaveSettings>,
+    video_applied: &mut EventWriter<VideoSettingsApplied>,
+) {
+    match draft.apply() {
+        Some(previous) => {
+            video_applied.write(VideoSettingsApplied { previous });
+        }
+        None => {
+            save.write(SaveSettings);
+        }
+    }
+}
+
+/// Spawns the label of a settings entry into the first central column of grid row `row`
+/// and a button reverting the entry into the third one. Both get the `field` marker.
+/// Returns the modifier for the control placed between them.
+pub fn settings_row(
+    parent: &mut RelatedSpawnerCommands<ChildOf>,
+    label: &str,
+    field: impl Component + Clone,
+    font: Handle<Font>,
+    row: i16,
+    ui_style: &UiStyle,
+) -> NodeModifier {
+    parent.spawn((
+        text_box(
+            label,
+            font.clone(),
+            NodeModifier::new()
+                .set_grid_column(GridPlacement::start_span(2, 1))
+                .set_grid_row(GridPlacement::start_span(row, 1)),
+            StyleRole::Label,
+            ui_style,
+        ),
+        SettingLabel(label.to_string()),
+        Modified::default(),
+        field.clone(),
+    ));
+    parent
+        .spawn((
+            button_box(
+                "Undo",
+                font,
+                NodeModifier::new()
+                    .set_grid_column(GridPlacement::start_span(4, 1))
+                    .set_grid_row(GridPlacement::start_span(row, 1)),
+                ui_style,
+            ),
+            RevertFieldButton,
+            Tooltip::new("Go back to the applied value"),
+            Modified::default(),
+            Visibility::Hidden,
+            field,
+        ))
+        .observe(on_revert_field_clicked);
+    NodeModifier::new()
+        .set_grid_column(GridPlacement::start_span(3, 1))
+        .set_grid_row(GridPlacement::start_span(row, 1))
+}
+
+/// Spawns the Apply, Cancel, Reset to defaults and Quit buttons into grid row `row`.
+pub fn spawn_page_buttons(
+    parent: &mut RelatedSpawnerCommands<ChildOf>,
+    ui_assets: &UIAssets,
+    ui_style: &UiStyle,
+    row: i16,
+) {
+    let font = ui_assets
+        .font
+        .clone()
+        .expect("Font should be loaded already");
+    let modifier = NodeModifier::new()
+        .set_grid_column(GridPlacement::start_span(2, 3))
+        .set_grid_row(GridPlacement::start_span(row, 1));
+    vertically_centered(parent, Name::new("PageButtons"), modifier, |parent| {
+        parent
+            .spawn((
+                button_box("Apply", font.clone(), NodeModifier::new(), ui_style),
+                Tooltip::new("Apply the changes made on every tab"),
+            ))
+            .observe(on_apply_clicked);
+        parent
+            .spawn((
+                button_box("Cancel", font.clone(), NodeModifier::new(), ui_style),
+                Tooltip::new("Throw away the changes that weren't applied"),
+            ))
+            .observe(on_cancel_clicked);
+        parent
+            .spawn((
+                button_box(
+                    "Reset to defaults",
+                    font.clone(),
+                    NodeModifier::new(),
+                    ui_style,
+                ),
+                Tooltip::new("Set every setting back to its default, applied with Apply"),
+            ))
+            .observe(on_reset_defaults_clicked);
+        parent
+            .spawn(button_box(
+                "Quit",
+                font.clone(),
+                NodeModifier::new(),
+                ui_style,
+            ))
+            .observe(on_quit_clicked);
+    });
+}
+
+fn on_revert_field_clicked(
+    mut trigger: Trigger<Pointer<Click>>,
+    mut revert: EventWriter<RevertField>,
+) {
+    trigger.propagate(false);
+    revert.write(RevertField {
+        entity: trigger.target(),
+    });
+}
+
+fn on_apply_clicked(
+    mut trigger: Trigger<Pointer<Click>>,
+    mut draft: SettingsDraft,
+    mut save: EventWriter<SaveSettings>,
+    mut video_applied: EventWriter<VideoSettingsApplied>,
+) {
+    trigger.propagate(false);
+    apply_staged_settings(&mut draft, &mut save, &mut video_applied);
+}
+
+fn on_cancel_clicked(mut trigger: Trigger<Pointer<Click>>, mut draft: SettingsDraft) {
+    trigger.propagate(false);
+    draft.cancel();
+}
+
+fn on_reset_defaults_clicked(mut trigger: Trigger<Pointer<Click>>, mut draft: SettingsDraft) {
+    trigger.propagate(false);
+    draft.reset_to_defaults();
+}
+
+fn on_quit_clicked(
+    mut trigger: Trigger<Pointer<Click>>,
+    mut leave: EventWriter<LeaveSettingsPage>,
+) {
+    trigger.propagate(false);
+    leave.write(LeaveSettingsPage(SettingsDestination::Quit));
+}
+
+fn leave_settings_page(destination: SettingsDestination, exit: &mut EventWriter<AppExit>) {
+    info!("Leaving the settings page to {destination:?}");
+    match destination {
+        SettingsDestination::Quit => {
+            exit.write(AppExit::Success);
+        }
+    }
+}
+
+fn on_leave_settings_page(
+    mut commands: Commands,
+    mut events: EventReader<LeaveSettingsPage>,
+    draft: SettingsDraft,
+    mut pending: ResMut<PendingLeave>,
+    mut exit: EventWriter<AppExit>,
+    ui_assets: Res<UIAssets>,
+    ui_style: Res<UiStyle>,
+) {
+    for LeaveSettingsPage(destination) in events.read() {
+        if !draft.is_dirty() {
+            leave_settings_page(*destination, &mut exit);
+        } else if pending.0.replace(*destination).is_none() {
+            spawn_unsaved_changes_dialog(&mut commands, &ui_assets, &ui_style);
+        }
+    }
+}
+
+fn spawn_unsaved_changes_dialog(commands: &mut Commands, ui_assets: &UIAssets, ui_style: &UiStyle) {
+    let dialog = ModalDialog::new("Unsaved changes", "You have unsaved changes.")
+        .with_buttons(["Apply", "Discard", "Stay"])
+        .with_escape_button(2);
+    let entity = spawn_modal(
+        commands,
+        (UnsavedChangesDialog, Name::new("UnsavedChanges")),
+        dialog,
+        ui_assets,
+        ui_style,
+    );
+    commands.entity(entity).observe(on_unsaved_changes_response);
+}
+
+/// Applying on the way out saves right away, there's no staying around
+/// to confirm new video settings.
+fn on_unsaved_changes_response(
+    trigger: Trigger<ModalResponse>,
+    mut draft: SettingsDraft,
+    mut pending: ResMut<PendingLeave>,
+    mut save: EventWriter<SaveSettings>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let Some(destination) = pending.0.take() else {
+        return;
+    };
+    match trigger.event().button {
+        // Apply
+        0 => {
+            draft.apply();
+            save.write(SaveSettings);
+        }
+        // Discard
+        1 => draft.cancel(),
+        // Stay
+        _ => return,
+    }
+    leave_settings_page(destination, &mut exit);
+}
+
+fn update_modified_labels(
+    labels: Query<(&SettingLabel, Ref<Modified>, &Children)>,
+    mut text_query: Query<(&mut Text, &mut TextColor)>,
+    ui_style: Res<UiStyle>,
+) {
+    for (label, modified, children) in labels {
+        if !modified.is_changed() && !ui_style.is_changed() {
+            continue;
+        }
+        let (text, color) = if modified.0 {
+            (
+                format!("{} *", label.0),
+                ui_style.detail_colors.modified_label,
+            )
+        } else {
+            (label.0.clone(), ui_style.label_color)
+        };
+        let mut iter = text_query.iter_many_mut(children);
+        while let Some((mut child_text, mut child_color)) = iter.fetch_next() {
+            child_text.set_if_neq(Text(text.clone()));
+            child_color.set_if_neq(TextColor(color));
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_revert_buttons(
+    buttons: Query<(&Modified, &mut Visibility), (With<RevertFieldButton>, Changed<Modified>)>,
+) {
+    for (modified, mut visibility) in buttons {
+        visibility.set_if_neq(if modified.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+pub struct StagingPlugin;
+
+impl Plugin for StagingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingLeave>()
+            .add_event::<RevertField>()
+            .add_event::<LeaveSettingsPage>()
+            .add_systems(Startup, stage_settings)
+            .add_systems(
+                Update,
+                (
+                    on_leave_settings_page.run_if(on_event::<LeaveSettingsPage>),
+                    update_modified_labels.after(restyle_widgets),
+                    update_revert_buttons,
+                )
+                    .chain(),
+            );
+    }
+}
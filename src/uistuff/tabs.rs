@@ -0,0 +1,476 @@
+use crate::uistuff::utils::*;
+use bevy::{
+    ecs::relationship::RelatedSpawnerCommands,
+    input::{ButtonState, gamepad::GamepadButtonChangedEvent, keyboard::KeyboardInput},
+    input_focus::{FocusedInput, InputFocus},
+    prelude::*,
+};
+
+use super::{
+    config::UiStyle,
+    layouts::{NodeModifier, button_box},
+};
+
+/// A row of tab headers above a content area showing the page of the active tab.
+/// Switch tabs by clicking the headers, with Q/E or with the gamepad shoulder buttons.
+#[derive(Component, Clone, Debug, Default)]
+pub struct TabView {
+    pub labels: Vec<String>,
+    pub active: usize,
+}
+
+impl TabView {
+    pub fn new(labels: impl IntoIterator<Item = impl Into<String>>, active: usize) -> Self {
+        Self {
+            labels: labels.into_iter().map(Into::into).collect(),
+            active,
+        }
+    }
+}
+
+/// Tab views with this component don't switch by themselves.
+/// They send [`TabChangeRequested`] instead and whoever handles it sets [`TabView::active`].
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct ConfirmTabChange;
+
+/// Sent when the active tab of a tab view changed.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TabChanged {
+    pub tab_view: Entity,
+    pub index: usize,
+}
+
+/// Sent instead of switching tabs on views with [`ConfirmTabChange`].
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TabChangeRequested {
+    pub tab_view: Entity,
+    pub index: usize,
+}
+
+/// The header of the tab with this index.
+#[derive(Component, Clone, Copy, Debug, Deref)]
+struct TabHeader(usize);
+
+/// The page shown while the tab with this index is active.
+#[derive(Component, Clone, Copy, Debug, Deref)]
+pub struct TabPage(pub usize);
+
+fn page_display(active: bool) -> Display {
+    if active { Display::Flex } else { Display::None }
+}
+
+/// Spawns a tab view. `page_spawner` is called once per tab with its index
+/// and fills that tab's page.
+pub fn spawn_tab_view(
+    mut commands: impl GenericSpawner,
+    extra_components: impl Bundle,
+    tab_view: TabView,
+    node_modifier: NodeModifier,
+    ui_assets: &UIAssets,
+    ui_style: &UiStyle,
+    mut page_spawner: impl FnMut(usize, &mut RelatedSpawnerCommands<ChildOf>),
+) -> Entity {
+    let font = ui_assets
+        .font
+        .clone()
+        .expect("Font should be loaded already");
+    let labels = tab_view.labels.clone();
+    let active = tab_view.active;
+    commands
+        .generic_spawn((
+            extra_components,
+            tab_view,
+            Name::new("TabView"),
+            node_modifier.modify(Node {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Stretch,
+                min_width: Val::Percent(100.0),
+                row_gap: Val::Px(ui_style.spacing.medium),
+                ..Default::default()
+            }),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Name::new("TabHeaders"),
+                    Node {
+                        display: Display::Flex,
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Center,
+                        column_gap: Val::Px(ui_style.spacing.medium),
+                        ..Default::default()
+                    },
+                ))
+                .with_children(|parent| {
+                    for (index, label) in labels.iter().enumerate() {
+                        let mut header = parent.spawn((
+                            button_box(label, font.clone(), NodeModifier::new(), ui_style),
+                            TabHeader(index),
+                        ));
+                        if index == active {
+                            header.insert(Selected);
+                        }
+                        header.observe(on_tab_header_clicked);
+                    }
+                });
+            for (index, label) in labels.iter().enumerate() {
+                parent
+                    .spawn((
+                        TabPage(index),
+                        Name::new(format!("TabPage {label}")),
+                        Node {
+                            display: page_display(index == active),
+                            flex_direction: FlexDirection::Column,
+                            ..Default::default()
+                        },
+                    ))
+                    .with_children(|parent| page_spawner(index, parent));
+            }
+        })
+        .id()
+}
+
+/// Activates tab `index`, or asks for it on views with [`ConfirmTabChange`].
+fn switch_tab(
+    entity: Entity,
+    tab_view: &mut Mut<TabView>,
+    index: usize,
+    confirm: bool,
+    requests: &mut EventWriter<TabChangeRequested>,
+) {
+    if index == tab_view.active || index >= tab_view.labels.len() {
+        return;
+    }
+    if confirm {
+        requests.write(TabChangeRequested {
+            tab_view: entity,
+            index,
+        });
+    } else {
+        tab_view.active = index;
+    }
+}
+
+fn on_tab_header_clicked(
+    mut trigger: Trigger<Pointer<Click>>,
+    headers: Query<&TabHeader>,
+    parent_query: Query<&ChildOf>,
+    mut tab_views: Query<(&mut TabView, Has<ConfirmTabChange>)>,
+    mut requests: EventWriter<TabChangeRequested>,
+) {
+    let Ok(header) = headers.get(trigger.target()) else {
+        return;
+    };
+    trigger.propagate(false);
+    let Some(entity) = parent_query
+        .iter_ancestors(trigger.target())
+        .find(|e| tab_views.contains(*e))
+    else {
+        return;
+    };
+    let (mut tab_view, confirm) = tab_views.get_mut(entity).expect("Checked above");
+    switch_tab(entity, &mut tab_view, header.0, confirm, &mut requests);
+}
+
+/// Whether `entity` sits on the page of an inactive tab. Pages are hidden with
+/// [`Display::None`], which doesn't change [`InheritedVisibility`].
+fn on_hidden_page(
+    entity: Entity,
+    parent_query: &Query<&ChildOf>,
+    pages: &Query<&Node, With<TabPage>>,
+) -> bool {
+    parent_query
+        .iter_ancestors(entity)
+        .filter_map(|e| pages.get(e).ok())
+        .any(|node| node.display == Display::None)
+}
+
+/// Moves every shown tab view by `offset` tabs, wrapping around at both ends.
+fn switch_shown_tabs(
+    offset: isize,
+    tab_views: &mut Query<(
+        Entity,
+        &mut TabView,
+        Has<ConfirmTabChange>,
+        &InheritedVisibility,
+    )>,
+    parent_query: &Query<&ChildOf>,
+    pages: &Query<&Node, With<TabPage>>,
+    requests: &mut EventWriter<TabChangeRequested>,
+) {
+    for (entity, mut tab_view, confirm, visibility) in tab_views.iter_mut() {
+        let count = tab_view.labels.len() as isize;
+        if !visibility.get() || count == 0 || on_hidden_page(entity, parent_query, pages) {
+            continue;
+        }
+        let index = (tab_view.active as isize + offset).rem_euclid(count) as usize;
+        switch_tab(entity, &mut tab_view, index, confirm, requests);
+    }
+}
+
+/// Q and E switch tabs unless the focused widget used the key itself,
+/// so only input that bubbled up to the window is considered.
+#[allow(clippy::type_complexity)]
+fn on_tab_key(
+    trigger: Trigger<FocusedInput<KeyboardInput>>,
+    windows: Query<(), With<Window>>,
+    mut tab_views: Query<(
+        Entity,
+        &mut TabView,
+        Has<ConfirmTabChange>,
+        &InheritedVisibility,
+    )>,
+    parent_query: Query<&ChildOf>,
+    pages: Query<&Node, With<TabPage>>,
+    mut requests: EventWriter<TabChangeRequested>,
+) {
+    let input = &trigger.event().input;
+    if !windows.contains(trigger.target()) || input.state != ButtonState::Pressed || input.repeat {
+        return;
+    }
+    let offset = match input.key_code {
+        KeyCode::KeyQ => -1,
+        KeyCode::KeyE => 1,
+        _ => return,
+    };
+    switch_shown_tabs(offset, &mut tab_views, &parent_query, &pages, &mut requests);
+}
+
+#[allow(clippy::type_complexity)]
+fn on_tab_gamepad_button(
+    trigger: Trigger<FocusedInput<GamepadButtonChangedEvent>>,
+    windows: Query<(), With<Window>>,
+    mut tab_views: Query<(
+        Entity,
+        &mut TabView,
+        Has<ConfirmTabChange>,
+        &InheritedVisibility,
+    )>,
+    parent_query: Query<&ChildOf>,
+    pages: Query<&Node, With<TabPage>>,
+    mut requests: EventWriter<TabChangeRequested>,
+) {
+    let input = &trigger.event().input;
+    if !windows.contains(trigger.target()) || input.state != ButtonState::Pressed {
+        return;
+    }
+    let offset = match input.button {
+        GamepadButton::LeftTrigger => -1,
+        GamepadButton::RightTrigger => 1,
+        _ => return,
+    };
+    switch_shown_tabs(offset, &mut tab_views, &parent_query, &pages, &mut requests);
+}
+
+/// Shows the page of the active tab, marks its header [`Selected`] and sends
+/// [`TabChanged`]. The focus is dropped when its widget ends up on a hidden page.
+#[allow(clippy::too_many_arguments)]
+fn update_tab_views(
+    mut commands: Commands,
+    tab_views: Query<(Entity, Ref<TabView>)>,
+    children_query: Query<&Children>,
+    headers: Query<&TabHeader>,
+    mut pages: Query<(&TabPage, &mut Node)>,
+    parent_query: Query<&ChildOf>,
+    mut focus: ResMut<InputFocus>,
+    mut changed: EventWriter<TabChanged>,
+) {
+    for (entity, tab_view) in tab_views {
+        if !tab_view.is_changed() || tab_view.is_added() {
+            continue;
+        }
+        let active = tab_view.active;
+        // Only look at this view's own headers and pages, not at tab views nested in them.
+        for child in children_query.relationship_sources(entity) {
+            for grandchild in children_query.relationship_sources(child) {
+                if let Ok(header) = headers.get(grandchild) {
+                    if header.0 == active {
+                        commands.entity(grandchild).insert(Selected);
+                    } else {
+                        commands.entity(grandchild).remove::<Selected>();
+                    }
+                }
+            }
+            if let Ok((page, mut node)) = pages.get_mut(child) {
+                node.display = page_display(page.0 == active);
+                let hides_focus = focus.0.is_some_and(|focused| {
+                    std::iter::once(focused)
+                        .chain(parent_query.iter_ancestors(focused))
+                        .any(|e| e == child)
+                });
+                if page.0 != active && hides_focus {
+                    focus.0 = None;
+                }
+            }
+        }
+        info!("Switched to tab {active}");
+        changed.write(TabChanged {
+            tab_view: entity,
+            index: active,
+        });
+    }
+}
+
+pub struct TabsPlugin;
+
+impl Plugin for TabsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TabChanged>()
+            .add_event::<TabChangeRequested>()
+            .add_observer(on_tab_key)
+            .add_observer(on_tab_gamepad_button)
+            .add_systems(Update, update_tab_views);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uistuff::utils::test_input::{
+        add_input_dispatch, click, press_gamepad_button, press_key,
+    };
+    use bevy::ecs::system::RunSystemOnce;
+
+    struct TestTabs {
+        view: Entity,
+        headers: Vec<Entity>,
+        pages: Vec<Entity>,
+    }
+
+    /// An app with a shown tab view of three tabs, past its first update.
+    fn tab_app(confirm: bool) -> (App, Entity, TestTabs) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, TabsPlugin));
+        let window = add_input_dispatch(&mut app);
+        let world = app.world_mut();
+        let view = world
+            .spawn((
+                TabView::new(["A", "B", "C"], 0),
+                Node::default(),
+                InheritedVisibility::VISIBLE,
+            ))
+            .id();
+        if confirm {
+            world.entity_mut(view).insert(ConfirmTabChange);
+        }
+        let header_row = world.spawn((Node::default(), ChildOf(view))).id();
+        let headers: Vec<Entity> = (0..3)
+            .map(|index| {
+                world
+                    .spawn((TabHeader(index), ChildOf(header_row)))
+                    .observe(on_tab_header_clicked)
+                    .id()
+            })
+            .collect();
+        let pages = (0..3)
+            .map(|index| {
+                let node = Node {
+                    display: page_display(index == 0),
+                    ..Default::default()
+                };
+                world.spawn((TabPage(index), node, ChildOf(view))).id()
+            })
+            .collect();
+        app.update();
+        let tabs = TestTabs {
+            view,
+            headers,
+            pages,
+        };
+        (app, window, tabs)
+    }
+
+    fn active(app: &App, tabs: &TestTabs) -> usize {
+        app.world().get::<TabView>(tabs.view).unwrap().active
+    }
+
+    fn sent<E: Event + Copy>(app: &App) -> Vec<E> {
+        let events = app.world().resource::<Events<E>>();
+        events.get_cursor().read(events).copied().collect()
+    }
+
+    #[test]
+    fn clicking_a_header_switches_tabs() {
+        let (mut app, _, tabs) = tab_app(false);
+        click(&mut app, tabs.headers[2]);
+        app.update();
+        assert_eq!(active(&app, &tabs), 2);
+        let changed: Vec<_> = sent::<TabChanged>(&app)
+            .iter()
+            .map(|e| (e.tab_view, e.index))
+            .collect();
+        assert_eq!(changed, [(tabs.view, 2)]);
+        let display = |page: Entity| app.world().get::<Node>(page).unwrap().display;
+        assert_eq!(display(tabs.pages[0]), Display::None);
+        assert_eq!(display(tabs.pages[2]), Display::Flex);
+        assert!(app.world().get::<Selected>(tabs.headers[2]).is_some());
+        assert!(app.world().get::<Selected>(tabs.headers[0]).is_none());
+    }
+
+    #[test]
+    fn q_and_e_switch_tabs_and_wrap_around() {
+        let (mut app, window, tabs) = tab_app(false);
+        press_key(&mut app, window, KeyCode::KeyE);
+        assert_eq!(active(&app, &tabs), 1);
+        press_key(&mut app, window, KeyCode::KeyQ);
+        assert_eq!(active(&app, &tabs), 0);
+        press_key(&mut app, window, KeyCode::KeyQ);
+        assert_eq!(active(&app, &tabs), 2);
+        press_key(&mut app, window, KeyCode::KeyE);
+        assert_eq!(active(&app, &tabs), 0);
+    }
+
+    #[test]
+    fn shoulder_buttons_switch_tabs() {
+        let (mut app, _, tabs) = tab_app(false);
+        press_gamepad_button(&mut app, GamepadButton::RightTrigger);
+        assert_eq!(active(&app, &tabs), 1);
+        assert_eq!(sent::<TabChanged>(&app).len(), 1);
+        press_gamepad_button(&mut app, GamepadButton::LeftTrigger);
+        assert_eq!(active(&app, &tabs), 0);
+    }
+
+    #[test]
+    fn confirmed_views_only_request_a_change() {
+        let (mut app, window, tabs) = tab_app(true);
+        click(&mut app, tabs.headers[1]);
+        press_key(&mut app, window, KeyCode::KeyQ);
+        assert_eq!(active(&app, &tabs), 0);
+        let requested: Vec<_> = sent::<TabChangeRequested>(&app)
+            .iter()
+            .map(|e| (e.tab_view, e.index))
+            .collect();
+        assert_eq!(requested, [(tabs.view, 1), (tabs.view, 2)]);
+        assert!(sent::<TabChanged>(&app).is_empty());
+    }
+
+    #[test]
+    fn views_on_inactive_pages_are_hidden() {
+        let mut world = World::new();
+        let shown_page = world.spawn((TabPage(0), Node::default())).id();
+        let hidden_page = world
+            .spawn((
+                TabPage(1),
+                Node {
+                    display: Display::None,
+                    ..Default::default()
+                },
+            ))
+            .id();
+        let shown = world.spawn((Node::default(), ChildOf(shown_page))).id();
+        let nested = world.spawn((Node::default(), ChildOf(hidden_page))).id();
+        let hidden = world.spawn((Node::default(), ChildOf(nested))).id();
+        let result = world
+            .run_system_once(
+                move |parent_query: Query<&ChildOf>, pages: Query<&Node, With<TabPage>>| {
+                    (
+                        on_hidden_page(shown, &parent_query, &pages),
+                        on_hidden_page(hidden, &parent_query, &pages),
+                    )
+                },
+            )
+            .unwrap();
+        assert_eq!(result, (false, true));
+    }
+}
@@ -0,0 +1,588 @@
+use crate::uistuff::utils::*;
+use bevy::{
+    input::{ButtonState, keyboard::KeyboardInput},
+    input_focus::{FocusedInput, InputFocus},
+    prelude::*,
+};
+
+use super::{
+    config::{StyleColor, UiStyle},
+    layouts::NodeModifier,
+};
+
+/// How long the caret stays visible, then hidden, while blinking.
+const CARET_BLINK_SECONDS: f32 = 0.5;
+
+/// Decides which characters a [`TextInput`] accepts.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum CharFilter {
+    #[default]
+    Any,
+    Alphanumeric,
+    Digits,
+    /// Digits, a minus sign and a decimal point.
+    Decimal,
+    Custom(fn(char) -> bool),
+}
+
+impl CharFilter {
+    pub fn accepts(&self, c: char) -> bool {
+        if c.is_control() {
+            return false;
+        }
+        match self {
+            CharFilter::Any => true,
+            CharFilter::Alphanumeric => c.is_alphanumeric(),
+            CharFilter::Digits => c.is_ascii_digit(),
+            CharFilter::Decimal => c.is_ascii_digit() || c == '-' || c == '.',
+            CharFilter::Custom(f) => f(c),
+        }
+    }
+}
+
+/// A single-line editable text field.
+/// Positions (`caret`, `anchor`) count characters, not bytes.
+#[derive(Component, Clone, Debug, Default)]
+#[require(Focusable)]
+pub struct TextInput {
+    pub value: String,
+    pub caret: usize,
+    /// The other end of the selection, if any text is selected.
+    pub anchor: Option<usize>,
+    pub max_length: Option<usize>,
+    pub filter: CharFilter,
+    pub placeholder: String,
+}
+
+impl TextInput {
+    pub fn new(value: impl Into<String>) -> Self {
+        let value = value.into();
+        Self {
+            caret: value.chars().count(),
+            value,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    pub fn with_filter(mut self, filter: CharFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_placeholder(mut self, placeholder: impl Into<String>) -> Self {
+        self.placeholder = placeholder.into();
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.value.chars().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    /// Replaces the whole value, moving the caret to the end.
+    pub fn set_value(&mut self, value: impl Into<String>) {
+        self.value = value.into();
+        self.caret = self.len();
+        self.anchor = None;
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(char_index)
+            .map_or(self.value.len(), |(i, _)| i)
+    }
+
+    /// The selected character range, if the selection isn't empty.
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        self.anchor
+            .filter(|anchor| *anchor != self.caret)
+            .map(|anchor| (anchor.min(self.caret), anchor.max(self.caret)))
+    }
+
+    pub fn selected_text(&self) -> &str {
+        match self.selection() {
+            Some((start, end)) => &self.value[self.byte_index(start)..self.byte_index(end)],
+            None => "",
+        }
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.caret = self.len();
+    }
+
+    /// Moves the caret, extending the selection when `select` is set.
+    pub fn move_caret(&mut self, to: usize, select: bool) {
+        let to = to.min(self.len());
+        if select {
+            self.anchor.get_or_insert(self.caret);
+        } else {
+            self.anchor = None;
+        }
+        self.caret = to;
+    }
+
+    fn delete_range(&mut self, start: usize, end: usize) {
+        let (start_byte, end_byte) = (self.byte_index(start), self.byte_index(end));
+        self.value.replace_range(start_byte..end_byte, "");
+        self.caret = start;
+        self.anchor = None;
+    }
+
+    fn delete_selection(&mut self) -> bool {
+        match self.selection() {
+            Some((start, end)) => {
+                self.delete_range(start, end);
+                true
+            }
+            None => {
+                self.anchor = None;
+                false
+            }
+        }
+    }
+
+    /// Inserts the accepted characters of `text` at the caret, replacing the selection.
+    /// Returns whether the value changed.
+    /// The selection is left alone when none of the characters are accepted.
+    pub fn insert(&mut self, text: &str) -> bool {
+        let selected = self.selection().map_or(0, |(start, end)| end - start);
+        let room = self
+            .max_length
+            .map_or(usize::MAX, |max| max.saturating_sub(self.len() - selected));
+        let accepted: String = text
+            .chars()
+            .filter(|c| self.filter.accepts(*c))
+            .take(room)
+            .collect();
+        if accepted.is_empty() {
+            return false;
+        }
+        self.delete_selection();
+        let at = self.byte_index(self.caret);
+        self.value.insert_str(at, &accepted);
+        self.caret += accepted.chars().count();
+        true
+    }
+
+    /// Deletes the selection, or the character (or word) before the caret.
+    pub fn backspace(&mut self, word: bool) -> bool {
+        if self.delete_selection() {
+            return true;
+        }
+        let start = if word {
+            self.previous_word_start()
+        } else {
+            self.caret.saturating_sub(1)
+        };
+        if start == self.caret {
+            return false;
+        }
+        self.delete_range(start, self.caret);
+        true
+    }
+
+    /// Deletes the selection, or the character (or word) after the caret.
+    pub fn delete(&mut self, word: bool) -> bool {
+        if self.delete_selection() {
+            return true;
+        }
+        let end = if word {
+            self.next_word_start()
+        } else {
+            (self.caret + 1).min(self.len())
+        };
+        if end == self.caret {
+            return false;
+        }
+        self.delete_range(self.caret, end);
+        true
+    }
+
+    pub fn previous_word_start(&self) -> usize {
+        let chars: Vec<char> = self.value.chars().collect();
+        let mut i = self.caret.min(chars.len());
+        while i > 0 && chars[i - 1].is_whitespace() {
+            i -= 1;
+        }
+        while i > 0 && !chars[i - 1].is_whitespace() {
+            i -= 1;
+        }
+        i
+    }
+
+    pub fn next_word_start(&self) -> usize {
+        let chars: Vec<char> = self.value.chars().collect();
+        let mut i = self.caret.min(chars.len());
+        while i < chars.len() && !chars[i].is_whitespace() {
+            i += 1;
+        }
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        i
+    }
+}
+
+/// Sent whenever the value of a [`TextInput`] is edited.
+#[derive(Event, Clone, Debug)]
+pub struct TextInputChanged {
+    pub input: Entity,
+    pub value: String,
+}
+
+/// Sent when Enter is pressed in a focused [`TextInput`].
+#[derive(Event, Clone, Debug)]
+pub struct TextInputSubmitted {
+    pub input: Entity,
+    pub value: String,
+}
+
+/// Colors a [`TextInput`] draws its text with.
+#[derive(Component, Clone, Copy, Debug)]
+struct TextInputColors {
+    text: Color,
+    selected: Color,
+    placeholder: Color,
+}
+
+impl TextInputColors {
+    fn new(style: &UiStyle) -> Self {
+        let colors = style.button_style;
+        Self {
+            text: colors.hover_colors.fore_color,
+            selected: colors.normal_colors.back_color,
+            placeholder: colors.hover_colors.fore_color.with_alpha(0.5),
+        }
+    }
+}
+
+/// The text entity of a [`TextInput`]; its spans hold the text before the selection,
+/// the caret if it is at the selection start, the selection, the caret if it is at
+/// the selection end, and the rest of the text.
+#[derive(Component)]
+struct TextInputText;
+
+#[derive(Resource)]
+struct CaretBlink(Timer);
+
+impl Default for CaretBlink {
+    fn default() -> Self {
+        Self(Timer::from_seconds(
+            CARET_BLINK_SECONDS * 2.0,
+            TimerMode::Repeating,
+        ))
+    }
+}
+
+pub fn text_input_box(
+    text_input: TextInput,
+    font: Handle<Font>,
+    node_modifier: NodeModifier,
+    style: &UiStyle,
+) -> impl Bundle {
+    let text_font = TextFont {
+        font,
+        font_size: style.font_sizes.body,
+        ..Default::default()
+    };
+    (
+        node_modifier.modify(Node {
+            min_width: Val::Px(200.0),
+            border: UiRect::all(Val::Px(style.borders.normal)),
+            padding: UiRect::all(Val::Px(style.spacing.small)),
+            overflow: Overflow::clip_x(),
+            ..Default::default()
+        }),
+        Name::new("TextInput"),
+        text_input,
+        TextInputColors::new(style),
+        StyleColor::NormalBack.border(style),
+        StyleColor::NormalFore.background(style),
+        children![(
+            TextInputText,
+            Text::default(),
+            text_font.clone(),
+            StyleColor::HoverFore.text(style),
+            children![
+                (TextSpan::default(), text_font.clone()),
+                (TextSpan::default(), text_font.clone()),
+                (TextSpan::default(), text_font.clone()),
+                (TextSpan::default(), text_font.clone()),
+                (TextSpan::default(), text_font),
+            ],
+        )],
+    )
+}
+
+fn on_text_input_key(
+    mut trigger: Trigger<FocusedInput<KeyboardInput>>,
+    mut inputs: Query<&mut TextInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut blink: ResMut<CaretBlink>,
+    mut changed: EventWriter<TextInputChanged>,
+    mut submitted: EventWriter<TextInputSubmitted>,
+) {
+    let entity = trigger.target();
+    let Ok(mut input) = inputs.get_mut(entity) else {
+        return;
+    };
+    let event = &trigger.event().input;
+    if event.state != ButtonState::Pressed {
+        return;
+    }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let input = input.as_mut();
+    let edited = match event.key_code {
+        KeyCode::Enter | KeyCode::NumpadEnter => {
+            submitted.write(TextInputSubmitted {
+                input: entity,
+                value: input.value.clone(),
+            });
+            false
+        }
+        KeyCode::Backspace => input.backspace(ctrl),
+        KeyCode::Delete => input.delete(ctrl),
+        KeyCode::ArrowLeft => {
+            let to = if ctrl {
+                input.previous_word_start()
+            } else if let (Some((start, _)), false) = (input.selection(), shift) {
+                start
+            } else {
+                input.caret.saturating_sub(1)
+            };
+            input.move_caret(to, shift);
+            false
+        }
+        KeyCode::ArrowRight => {
+            let to = if ctrl {
+                input.next_word_start()
+            } else if let (Some((_, end)), false) = (input.selection(), shift) {
+                end
+            } else {
+                input.caret + 1
+            };
+            input.move_caret(to, shift);
+            false
+        }
+        KeyCode::Home => {
+            input.move_caret(0, shift);
+            false
+        }
+        KeyCode::End => {
+            input.move_caret(input.len(), shift);
+            false
+        }
+        KeyCode::KeyA if ctrl => {
+            input.select_all();
+            false
+        }
+        // Let other shortcuts and keys without text (Tab, Escape...) bubble up.
+        _ if ctrl => return,
+        _ => match event.text {
+            Some(ref text) if !text.chars().all(char::is_control) => input.insert(text),
+            _ => return,
+        },
+    };
+    trigger.propagate(false);
+    blink.0.reset();
+    if edited {
+        changed.write(TextInputChanged {
+            input: entity,
+            value: input.value.clone(),
+        });
+    }
+}
+
+fn blink_caret(time: Res<Time>, mut blink: ResMut<CaretBlink>) {
+    blink.0.tick(time.delta());
+}
+
+fn restyle_text_inputs(ui_style: Res<UiStyle>, inputs: Query<&mut TextInputColors>) {
+    for mut colors in inputs {
+        *colors = TextInputColors::new(&ui_style);
+    }
+}
+
+fn update_text_input_visuals(
+    inputs: Query<(Entity, &TextInput, &TextInputColors, &Children)>,
+    text_query: Query<&Children, With<TextInputText>>,
+    mut spans: Query<(&mut TextSpan, &mut TextColor)>,
+    focus: Res<InputFocus>,
+    blink: Res<CaretBlink>,
+) {
+    let caret_visible = blink.0.elapsed_secs() < CARET_BLINK_SECONDS;
+    for (entity, input, colors, children) in inputs {
+        let focused = focus.0 == Some(entity);
+        let (start, end) = input.selection().unwrap_or((input.caret, input.caret));
+        let value = &input.value;
+        let (start_byte, end_byte) = (input.byte_index(start), input.byte_index(end));
+        let caret = if focused { "|" } else { "" };
+        let caret_color = if caret_visible {
+            colors.text
+        } else {
+            Color::NONE
+        };
+        let contents: [(&str, Color); 5] = if value.is_empty() && !focused {
+            [
+                (&input.placeholder, colors.placeholder),
+                ("", colors.text),
+                ("", colors.text),
+                ("", colors.text),
+                ("", colors.text),
+            ]
+        } else {
+            let caret_at_start = input.caret == start && start != end;
+            [
+                (&value[..start_byte], colors.text),
+                (if caret_at_start { caret } else { "" }, caret_color),
+                (&value[start_byte..end_byte], colors.selected),
+                (if caret_at_start { "" } else { caret }, caret_color),
+                (&value[end_byte..], colors.text),
+            ]
+        };
+        for text_children in text_query.iter_many(children) {
+            for (span_entity, (content, color)) in text_children.iter().zip(contents) {
+                if let Ok((mut span, mut span_color)) = spans.get_mut(span_entity) {
+                    if span.0 != content {
+                        span.0 = content.to_string();
+                    }
+                    span_color.set_if_neq(TextColor(color));
+                }
+            }
+        }
+    }
+}
+
+pub struct TextInputPlugin;
+
+impl Plugin for TextInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TextInputChanged>()
+            .add_event::<TextInputSubmitted>()
+            .init_resource::<CaretBlink>()
+            .add_observer(on_text_input_key)
+            .add_systems(
+                Update,
+                (
+                    blink_caret,
+                    restyle_text_inputs.run_if(resource_changed::<UiStyle>),
+                    update_text_input_visuals,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_at_caret() {
+        let mut input = TextInput::new("hello");
+        input.move_caret(2, false);
+        assert!(input.insert("XY"));
+        assert_eq!(input.value, "heXYllo");
+        assert_eq!(input.caret, 4);
+    }
+
+    #[test]
+    fn insert_replaces_selection() {
+        let mut input = TextInput::new("hello world");
+        input.move_caret(0, false);
+        input.move_caret(5, true);
+        assert_eq!(input.selected_text(), "hello");
+        assert!(input.insert("bye"));
+        assert_eq!(input.value, "bye world");
+        assert_eq!(input.caret, 3);
+        assert_eq!(input.selection(), None);
+    }
+
+    #[test]
+    fn rejected_char_keeps_selection() {
+        let mut input = TextInput::new("123").with_filter(CharFilter::Digits);
+        input.select_all();
+        assert!(!input.insert("a"));
+        assert_eq!(input.value, "123");
+        assert_eq!(input.selection(), Some((0, 3)));
+        assert!(input.insert("4"));
+        assert_eq!(input.value, "4");
+    }
+
+    #[test]
+    fn max_length() {
+        let mut input = TextInput::new("abc").with_max_length(5);
+        assert!(input.insert("defgh"));
+        assert_eq!(input.value, "abcde");
+        assert!(!input.insert("x"));
+        assert_eq!(input.value, "abcde");
+        // The selected characters make room for the inserted ones.
+        input.move_caret(3, true);
+        assert!(input.insert("xyz"));
+        assert_eq!(input.value, "abcxy");
+    }
+
+    #[test]
+    fn backspace_and_delete() {
+        let mut input = TextInput::new("héllo");
+        assert!(input.backspace(false));
+        assert_eq!(input.value, "héll");
+        input.move_caret(0, false);
+        assert!(!input.backspace(false));
+        assert!(input.delete(false));
+        assert_eq!(input.value, "éll");
+        input.move_caret(input.len(), false);
+        assert!(!input.delete(false));
+        input.move_caret(1, true);
+        assert!(input.delete(false));
+        assert_eq!(input.value, "é");
+    }
+
+    #[test]
+    fn word_jumps() {
+        let mut input = TextInput::new("one  two three");
+        assert_eq!(input.previous_word_start(), 9);
+        input.move_caret(9, false);
+        assert_eq!(input.previous_word_start(), 5);
+        input.move_caret(0, false);
+        assert_eq!(input.next_word_start(), 5);
+        input.move_caret(6, false);
+        assert_eq!(input.next_word_start(), 9);
+    }
+
+    #[test]
+    fn word_deletes() {
+        let mut input = TextInput::new("one two three");
+        assert!(input.backspace(true));
+        assert_eq!(input.value, "one two ");
+        input.move_caret(0, false);
+        assert!(input.delete(true));
+        assert_eq!(input.value, "two ");
+    }
+
+    #[test]
+    fn char_filters() {
+        assert!(CharFilter::Any.accepts('x'));
+        assert!(!CharFilter::Any.accepts('\n'));
+        assert!(CharFilter::Alphanumeric.accepts('ä'));
+        assert!(!CharFilter::Alphanumeric.accepts(' '));
+        assert!(CharFilter::Digits.accepts('7'));
+        assert!(!CharFilter::Digits.accepts('-'));
+        assert!(CharFilter::Decimal.accepts('-'));
+        assert!(CharFilter::Decimal.accepts('.'));
+        assert!(!CharFilter::Decimal.accepts('e'));
+        assert!(CharFilter::Custom(|c| c == 'q').accepts('q'));
+        assert!(!CharFilter::Custom(|c| c == 'q').accepts('r'));
+    }
+}
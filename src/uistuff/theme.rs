@@ -0,0 +1,343 @@
+use std::fmt;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader, ron},
+    color::palettes::{css, tailwind},
+    prelude::*,
+};
+use serde::{Deserialize, Deserializer};
+
+use super::config::UiStyle;
+
+/// Folder theme files are looked up in, inside the assets folder.
+pub const THEME_FOLDER: &str = "themes";
+
+/// Themes switched between by the dark theme toggle of the settings page.
+pub const LIGHT_THEME: &str = "standard";
+pub const DARK_THEME: &str = "dark";
+
+/// A [`UiStyle`] loaded from a `.theme.ron` file.
+///
+/// Colours are strings, either hex (`"#ff8800"`, `"#ff880080"`) or palette entries
+/// like `"tailwind:pink_400"` and `"css:white"`. Sections left out keep the
+/// colours of the standard style, and button states other than `normal_colors`
+/// are derived from the normal and hover colours when left out.
+/// Spacing, border widths, corner radii and font sizes are numbers in logical pixels.
+#[derive(Asset, TypePath, Clone, Copy, Debug, Deserialize)]
+#[serde(transparent)]
+pub struct Theme(pub UiStyle);
+
+/// Parses a hex colour or a `palette:name` entry of the css or tailwind palette.
+pub fn parse_color(value: &str) -> Result<Color, String> {
+    if value.starts_with('#') {
+        return Srgba::hex(value)
+            .map(Color::Srgba)
+            .map_err(|e| format!("{value}: {e}"));
+    }
+    let Some((palette, name)) = value.split_once(':') else {
+        return Err(format!(
+            "{value}: expected a hex colour or a palette entry like tailwind:pink_400"
+        ));
+    };
+    let name = name.to_uppercase();
+    let color = match palette.to_lowercase().as_str() {
+        "css" => css_color(&name),
+        "tailwind" => tailwind_color(&name),
+        _ => return Err(format!("{value}: unknown palette {palette}")),
+    };
+    color
+        .map(Color::Srgba)
+        .ok_or_else(|| format!("{value}: no colour {name} in the {palette} palette"))
+}
+
+/// For `#[serde(deserialize_with)]` on colour fields of theme types.
+pub fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_color(&value).map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug)]
+pub enum ThemeLoadError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for ThemeLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeLoadError::Io(e) => write!(f, "couldn't read theme: {e}"),
+            ThemeLoadError::Ron(e) => write!(f, "couldn't parse theme: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ThemeLoadError {}
+
+impl From<std::io::Error> for ThemeLoadError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<ron::error::SpannedError> for ThemeLoadError {
+    fn from(value: ron::error::SpannedError) -> Self {
+        Self::Ron(value)
+    }
+}
+
+#[derive(Default)]
+struct ThemeLoader;
+
+impl AssetLoader for ThemeLoader {
+    type Asset = Theme;
+    type Settings = ();
+    type Error = ThemeLoadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Theme, ThemeLoadError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["theme.ron"]
+    }
+}
+
+/// The theme file the UI style comes from. When the asset server watches for changes,
+/// its asset is reloaded whenever the file changes on disk, and every reload replaces
+/// the [`UiStyle`] resource.
+#[derive(Resource, Debug)]
+pub struct ActiveTheme {
+    pub name: String,
+    handle: Handle<Theme>,
+    /// Set until the theme has loaded and been applied, at startup and after switching.
+    pending: bool,
+}
+
+impl ActiveTheme {
+    /// Switches to the theme in `assets/themes/<name>.theme.ron`.
+    /// The UI is restyled once the theme has loaded.
+    pub fn switch(&mut self, name: impl Into<String>, asset_server: &AssetServer) {
+        self.name = name.into();
+        self.handle = asset_server.load(theme_asset_path(&self.name));
+        self.pending = true;
+    }
+}
+
+fn theme_asset_path(name: &str) -> String {
+    format!("{THEME_FOLDER}/{name}.theme.ron")
+}
+
+/// The theme named by `--theme <name>` in `args`, if any.
+fn theme_from_args(args: impl IntoIterator<Item = String>) -> Option<String> {
+    let mut args = args.into_iter().skip_while(|arg| arg != "--theme");
+    args.next()?;
+    args.next()
+}
+
+fn load_theme_asset(asset_server: Res<AssetServer>, mut theme: ResMut<ActiveTheme>) {
+    theme.handle = asset_server.load(theme_asset_path(&theme.name));
+}
+
+/// Applies the active theme once it has loaded and whenever its file was reloaded.
+/// Themes that fail to load keep the current style.
+fn apply_reloaded_theme(
+    mut events: EventReader<AssetEvent<Theme>>,
+    mut theme: ResMut<ActiveTheme>,
+    themes: Res<Assets<Theme>>,
+    mut ui_style: ResMut<UiStyle>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    let mut reloaded = false;
+    for event in events.read() {
+        reloaded |= event.is_modified(&theme.handle);
+    }
+    if !reloaded && !theme.pending {
+        return;
+    }
+    let Some(loaded) = themes.get(&theme.handle) else {
+        return;
+    };
+    theme.pending = false;
+    info!("Applying theme {}", theme.name);
+    *ui_style = loaded.0;
+    clear_color.0 = loaded.0.back_color;
+}
+
+/// Loads the [`UiStyle`] from `assets/themes/<theme>.theme.ron`. The standard style
+/// is used until the theme has loaded, and kept when it can't be loaded.
+/// Enable the asset plugin's `watch_for_changes_override` to reload edited themes.
+pub struct ThemePlugin {
+    pub theme: String,
+}
+
+impl Default for ThemePlugin {
+    fn default() -> Self {
+        Self {
+            theme: LIGHT_THEME.to_string(),
+        }
+    }
+}
+
+impl ThemePlugin {
+    /// Uses the theme given with `--theme <name>` on the command line, if any.
+    pub fn from_args() -> Self {
+        theme_from_args(std::env::args()).map_or_else(Self::default, |theme| Self { theme })
+    }
+}
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        let ui_style = UiStyle::default();
+        app.init_asset::<Theme>()
+            .init_asset_loader::<ThemeLoader>()
+            .insert_resource(ui_style)
+            .insert_resource(ClearColor(ui_style.back_color))
+            .insert_resource(ActiveTheme {
+                name: self.theme.clone(),
+                handle: Handle::default(),
+                pending: true,
+            })
+            .add_systems(Startup, load_theme_asset)
+            .add_systems(Update, apply_reloaded_theme);
+    }
+}
+
+macro_rules! palette_lookup {
+    ($fn_name:ident, $palette:ident, [$($color:ident),* $(,)?]) => {
+        /// The colour with this upper case name in the palette.
+        fn $fn_name(name: &str) -> Option<Srgba> {
+            match name {
+                $(stringify!($color) => Some($palette::$color),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+palette_lookup!(css_color, css, [
+    ALICE_BLUE, ANTIQUE_WHITE, AQUA, AQUAMARINE, AZURE, BEIGE, BISQUE, BLACK, BLANCHED_ALMOND,
+    BLUE, BLUE_VIOLET, BROWN, BURLYWOOD, CADET_BLUE, CHARTREUSE, CHOCOLATE, CORAL,
+    CORNFLOWER_BLUE, CORNSILK, CRIMSON, DARK_BLUE, DARK_CYAN, DARK_GOLDENROD, DARK_GRAY,
+    DARK_GREEN, DARK_GREY, DARK_KHAKI, DARK_MAGENTA, DARK_OLIVEGREEN, DARK_ORANGE, DARK_ORCHID,
+    DARK_RED, DARK_SALMON, DARK_SEA_GREEN, DARK_SLATE_BLUE, DARK_SLATE_GRAY, DARK_SLATE_GREY,
+    DARK_TURQUOISE, DARK_VIOLET, DEEP_PINK, DEEP_SKY_BLUE, DIM_GRAY, DIM_GREY, DODGER_BLUE,
+    FIRE_BRICK, FLORAL_WHITE, FOREST_GREEN, FUCHSIA, GAINSBORO, GHOST_WHITE, GOLD, GOLDENROD,
+    GRAY, GREEN, GREEN_YELLOW, GREY, HONEYDEW, HOT_PINK, INDIAN_RED, INDIGO, IVORY, KHAKI,
+    LAVENDER, LAVENDER_BLUSH, LAWN_GREEN, LEMON_CHIFFON, LIGHT_BLUE, LIGHT_CORAL, LIGHT_CYAN,
+    LIGHT_GOLDENROD_YELLOW, LIGHT_GRAY, LIGHT_GREEN, LIGHT_GREY, LIGHT_PINK, LIGHT_SALMON,
+    LIGHT_SEA_GREEN, LIGHT_SKY_BLUE, LIGHT_SLATE_GRAY, LIGHT_SLATE_GREY, LIGHT_STEEL_BLUE,
+    LIGHT_YELLOW, LIME, LIMEGREEN, LINEN, MAGENTA, MAROON, MEDIUM_AQUAMARINE, MEDIUM_BLUE,
+    MEDIUM_ORCHID, MEDIUM_PURPLE, MEDIUM_SEA_GREEN, MEDIUM_SLATE_BLUE, MEDIUM_SPRING_GREEN,
+    MEDIUM_TURQUOISE, MEDIUM_VIOLET_RED, MIDNIGHT_BLUE, MINT_CREAM, MISTY_ROSE, MOCCASIN,
+    NAVAJO_WHITE, NAVY, OLD_LACE, OLIVE, OLIVE_DRAB, ORANGE, ORANGE_RED, ORCHID, PALE_GOLDENROD,
+    PALE_GREEN, PALE_TURQUOISE, PALE_VIOLETRED, PAPAYA_WHIP, PEACHPUFF, PERU, PINK, PLUM,
+    POWDER_BLUE, PURPLE, REBECCA_PURPLE, RED, ROSY_BROWN, ROYAL_BLUE, SADDLE_BROWN, SALMON,
+    SANDY_BROWN, SEASHELL, SEA_GREEN, SIENNA, SILVER, SKY_BLUE, SLATE_BLUE, SLATE_GRAY,
+    SLATE_GREY, SNOW, SPRING_GREEN, STEEL_BLUE, TAN, TEAL, THISTLE, TOMATO, TURQUOISE, VIOLET,
+    WHEAT, WHITE, WHITE_SMOKE, YELLOW, YELLOW_GREEN,
+]);
+
+#[rustfmt::skip]
+palette_lookup!(tailwind_color, tailwind, [
+    AMBER_50, AMBER_100, AMBER_200, AMBER_300, AMBER_400, AMBER_500, AMBER_600, AMBER_700,
+    AMBER_800, AMBER_900, AMBER_950, BLUE_50, BLUE_100, BLUE_200, BLUE_300, BLUE_400, BLUE_500,
+    BLUE_600, BLUE_700, BLUE_800, BLUE_900, BLUE_950, CYAN_50, CYAN_100, CYAN_200, CYAN_300,
+    CYAN_400, CYAN_500, CYAN_600, CYAN_700, CYAN_800, CYAN_900, CYAN_950, EMERALD_50, EMERALD_100,
+    EMERALD_200, EMERALD_300, EMERALD_400, EMERALD_500, EMERALD_600, EMERALD_700, EMERALD_800,
+    EMERALD_900, EMERALD_950, FUCHSIA_50, FUCHSIA_100, FUCHSIA_200, FUCHSIA_300, FUCHSIA_400,
+    FUCHSIA_500, FUCHSIA_600, FUCHSIA_700, FUCHSIA_800, FUCHSIA_900, FUCHSIA_950, GRAY_50,
+    GRAY_100, GRAY_200, GRAY_300, GRAY_400, GRAY_500, GRAY_600, GRAY_700, GRAY_800, GRAY_900,
+    GRAY_950, GREEN_50, GREEN_100, GREEN_200, GREEN_300, GREEN_400, GREEN_500, GREEN_600,
+    GREEN_700, GREEN_800, GREEN_900, GREEN_950, INDIGO_50, INDIGO_100, INDIGO_200, INDIGO_300,
+    INDIGO_400, INDIGO_500, INDIGO_600, INDIGO_700, INDIGO_800, INDIGO_900, INDIGO_950, LIME_50,
+    LIME_100, LIME_200, LIME_300, LIME_400, LIME_500, LIME_600, LIME_700, LIME_800, LIME_900,
+    LIME_950, NEUTRAL_50, NEUTRAL_100, NEUTRAL_200, NEUTRAL_300, NEUTRAL_400, NEUTRAL_500,
+    NEUTRAL_600, NEUTRAL_700, NEUTRAL_800, NEUTRAL_900, NEUTRAL_950, ORANGE_50, ORANGE_100,
+    ORANGE_200, ORANGE_300, ORANGE_400, ORANGE_500, ORANGE_600, ORANGE_700, ORANGE_800,
+    ORANGE_900, ORANGE_950, PINK_50, PINK_100, PINK_200, PINK_300, PINK_400, PINK_500, PINK_600,
+    PINK_700, PINK_800, PINK_900, PINK_950, PURPLE_50, PURPLE_100, PURPLE_200, PURPLE_300,
+    PURPLE_400, PURPLE_500, PURPLE_600, PURPLE_700, PURPLE_800, PURPLE_900, PURPLE_950, RED_50,
+    RED_100, RED_200, RED_300, RED_400, RED_500, RED_600, RED_700, RED_800, RED_900, RED_950,
+    ROSE_50, ROSE_100, ROSE_200, ROSE_300, ROSE_400, ROSE_500, ROSE_600, ROSE_700, ROSE_800,
+    ROSE_900, ROSE_950, SKY_50, SKY_100, SKY_200, SKY_300, SKY_400, SKY_500, SKY_600, SKY_700,
+    SKY_800, SKY_900, SKY_950, SLATE_50, SLATE_100, SLATE_200, SLATE_300, SLATE_400, SLATE_500,
+    SLATE_600, SLATE_700, SLATE_800, SLATE_900, SLATE_950, STONE_50, STONE_100, STONE_200,
+    STONE_300, STONE_400, STONE_500, STONE_600, STONE_700, STONE_800, STONE_900, STONE_950,
+    TEAL_50, TEAL_100, TEAL_200, TEAL_300, TEAL_400, TEAL_500, TEAL_600, TEAL_700, TEAL_800,
+    TEAL_900, TEAL_950, VIOLET_50, VIOLET_100, VIOLET_200, VIOLET_300, VIOLET_400, VIOLET_500,
+    VIOLET_600, VIOLET_700, VIOLET_800, VIOLET_900, VIOLET_950, YELLOW_50, YELLOW_100, YELLOW_200,
+    YELLOW_300, YELLOW_400, YELLOW_500, YELLOW_600, YELLOW_700, YELLOW_800, YELLOW_900,
+    YELLOW_950, ZINC_50, ZINC_100, ZINC_200, ZINC_300, ZINC_400, ZINC_500, ZINC_600, ZINC_700,
+    ZINC_800, ZINC_900, ZINC_950,
+]);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(
+            parse_color("#ff8800"),
+            Ok(Color::Srgba(Srgba::rgb_u8(0xff, 0x88, 0x00)))
+        );
+        assert_eq!(
+            parse_color("#ff880080"),
+            Ok(Color::Srgba(Srgba::rgba_u8(0xff, 0x88, 0x00, 0x80)))
+        );
+        assert!(parse_color("#ff88f").is_err());
+        assert!(parse_color("#gg8800").is_err());
+    }
+
+    #[test]
+    fn parses_palette_entries() {
+        assert_eq!(parse_color("css:white"), Ok(Color::Srgba(css::WHITE)));
+        assert_eq!(
+            parse_color("tailwind:pink_400"),
+            Ok(Color::Srgba(tailwind::PINK_400))
+        );
+        assert_eq!(
+            parse_color("Tailwind:Pink_400"),
+            Ok(Color::Srgba(tailwind::PINK_400))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_colors() {
+        assert!(parse_color("white").is_err());
+        assert!(parse_color("crayola:red").is_err());
+        assert!(parse_color("css:pink_400").is_err());
+        assert!(parse_color("tailwind:").is_err());
+    }
+
+    #[test]
+    fn shipped_themes_parse() {
+        for text in [
+            include_str!("../../assets/themes/standard.theme.ron"),
+            include_str!("../../assets/themes/dark.theme.ron"),
+        ] {
+            if let Err(e) = ron::de::from_str::<Theme>(text) {
+                panic!("{e}");
+            }
+        }
+    }
+
+    #[test]
+    fn theme_argument() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            theme_from_args(args(&["game", "--theme", "dark"])),
+            Some("dark".to_string())
+        );
+        assert_eq!(theme_from_args(args(&["game", "--theme"])), None);
+        assert_eq!(theme_from_args(args(&["game"])), None);
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+
+use crate::uistuff::utils::*;
+use bevy::prelude::*;
+
+use super::{
+    config::{BackgroundForeground, StyleRole, UiStyle},
+    layouts::{NodeModifier, text_box},
+};
+
+/// Z-index of the toast area, above modals. Toasts never block input below them.
+pub const TOAST_Z_INDEX: i32 = 300;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Severity {
+    #[default]
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn colors(self, ui_style: &UiStyle) -> BackgroundForeground {
+        let colors = ui_style.severity_colors;
+        match self {
+            Severity::Info => colors.info,
+            Severity::Success => colors.success,
+            Severity::Warning => colors.warning,
+            Severity::Error => colors.error,
+        }
+    }
+}
+
+/// Send this to show a notification in the bottom right corner of the screen.
+#[derive(Event, Clone, Debug)]
+pub struct ShowToast {
+    pub message: String,
+    pub severity: Severity,
+    /// Seconds the toast stays up, [`ToastOptions::duration`] if `None`.
+    pub duration: Option<f32>,
+}
+
+impl ShowToast {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            severity,
+            duration: None,
+        }
+    }
+
+    pub fn with_duration(mut self, seconds: f32) -> Self {
+        self.duration = Some(seconds);
+        self
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ToastOptions {
+    /// Default seconds a toast stays up, including its fade-out.
+    pub duration: f32,
+    pub fade_duration: f32,
+    /// Toasts beyond this wait in a queue until older ones are gone.
+    pub max_visible: usize,
+}
+
+impl Default for ToastOptions {
+    fn default() -> Self {
+        Self {
+            duration: 4.0,
+            fade_duration: 0.5,
+            max_visible: 4,
+        }
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+struct ToastQueue(VecDeque<ShowToast>);
+
+#[derive(Component)]
+struct ToastArea;
+
+#[derive(Component, Debug)]
+struct Toast {
+    timer: Timer,
+}
+
+fn spawn_toast_area(mut commands: Commands, ui_style: Res<UiStyle>) {
+    commands.spawn((
+        ToastArea,
+        Name::new("ToastArea"),
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(ui_style.spacing.large),
+            bottom: Val::Px(ui_style.spacing.large),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::End,
+            row_gap: Val::Px(ui_style.spacing.medium),
+            ..Default::default()
+        },
+        GlobalZIndex(TOAST_Z_INDEX),
+        Pickable::IGNORE,
+    ));
+}
+
+fn queue_toasts(mut events: EventReader<ShowToast>, mut queue: ResMut<ToastQueue>) {
+    for event in events.read() {
+        info!("Toast: {}", event.message);
+        queue.0.push_back(event.clone());
+    }
+}
+
+/// Moves queued toasts on screen while there's room, newest at the bottom.
+fn show_queued_toasts(
+    mut commands: Commands,
+    mut queue: ResMut<ToastQueue>,
+    options: Res<ToastOptions>,
+    area: Single<Entity, With<ToastArea>>,
+    toasts: Query<(), With<Toast>>,
+    ui_assets: Res<UIAssets>,
+    ui_style: Res<UiStyle>,
+) {
+    let free = options.max_visible.saturating_sub(toasts.iter().count());
+    let count = free.min(queue.0.len());
+    for toast in queue.0.drain(..count) {
+        let font = ui_assets
+            .font
+            .clone()
+            .expect("Font should be loaded already");
+        let duration = toast.duration.unwrap_or(options.duration);
+        commands
+            .spawn((
+                text_box(
+                    toast.message,
+                    font,
+                    NodeModifier::new(),
+                    StyleRole::Toast(toast.severity),
+                    &ui_style,
+                ),
+                Toast {
+                    timer: Timer::from_seconds(duration, TimerMode::Once),
+                },
+                Name::new("Toast"),
+                ChildOf(*area),
+            ))
+            .observe(on_toast_clicked);
+    }
+}
+
+/// Clicking a toast starts its fade-out right away.
+fn on_toast_clicked(
+    mut trigger: Trigger<Pointer<Click>>,
+    mut toasts: Query<&mut Toast>,
+    options: Res<ToastOptions>,
+) {
+    trigger.propagate(false);
+    let Ok(mut toast) = toasts.get_mut(trigger.target()) else {
+        return;
+    };
+    let duration = toast.timer.duration().as_secs_f32();
+    let fade_start = (duration - options.fade_duration).max(0.0);
+    if toast.timer.elapsed_secs() < fade_start {
+        toast
+            .timer
+            .set_elapsed(std::time::Duration::from_secs_f32(fade_start));
+    }
+}
+
+/// Fades toasts out over their last [`ToastOptions::fade_duration`] seconds
+/// and despawns them once their time is up.
+fn update_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    options: Res<ToastOptions>,
+    ui_style: Res<UiStyle>,
+    toasts: Query<(
+        Entity,
+        &mut Toast,
+        &StyleRole,
+        &mut BackgroundColor,
+        &Children,
+    )>,
+    mut text_colors: Query<&mut TextColor>,
+) {
+    for (entity, mut toast, role, mut background, children) in toasts {
+        toast.timer.tick(time.delta());
+        if toast.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let alpha = if options.fade_duration > 0.0 {
+            (toast.timer.remaining_secs() / options.fade_duration).min(1.0)
+        } else {
+            1.0
+        };
+        let colors = role.colors(&ui_style).normal_colors;
+        background.set_if_neq(BackgroundColor(
+            colors
+                .back_color
+                .with_alpha(colors.back_color.alpha() * alpha),
+        ));
+        let mut iter = text_colors.iter_many_mut(children);
+        while let Some(mut color) = iter.fetch_next() {
+            color.set_if_neq(TextColor(
+                colors
+                    .fore_color
+                    .with_alpha(colors.fore_color.alpha() * alpha),
+            ));
+        }
+    }
+}
+
+pub struct ToastPlugin;
+
+impl Plugin for ToastPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ToastOptions>()
+            .init_resource::<ToastQueue>()
+            .add_event::<ShowToast>()
+            .add_systems(Startup, spawn_toast_area)
+            .add_systems(
+                Update,
+                (
+                    queue_toasts.run_if(on_event::<ShowToast>),
+                    // Fading overrides the colours the style gives toasts.
+                    update_toasts.after(restyle_widgets),
+                    show_queued_toasts,
+                )
+                    .chain(),
+            );
+    }
+}
@@ -0,0 +1,200 @@
+use crate::uistuff::utils::*;
+use bevy::{
+    input_focus::InputFocus,
+    picking::{hover::HoverMap, pointer::PointerId},
+    prelude::*,
+    window::PrimaryWindow,
+};
+
+use super::{
+    config::{StyleColor, UiStyle},
+    popup::{Popup, place_popup},
+};
+
+/// Z-index of the tooltip box, above everything else.
+pub const TOOLTIP_Z_INDEX: i32 = 400;
+
+/// Room left for the mouse cursor between the pointer and a tooltip below it,
+/// in logical pixels.
+const CURSOR_SIZE: Vec2 = Vec2::new(16.0, 20.0);
+
+/// Text shown in a box near the pointer after hovering the entity for
+/// [`TooltipOptions::delay`] seconds, or below the entity while it has the keyboard focus.
+#[derive(Component, Clone, Debug, Deref)]
+pub struct Tooltip(pub String);
+
+impl Tooltip {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self(text.into())
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct TooltipOptions {
+    /// Seconds before a tooltip appears.
+    pub delay: f32,
+}
+
+impl Default for TooltipOptions {
+    fn default() -> Self {
+        Self { delay: 0.6 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TooltipSource {
+    Pointer,
+    Focus,
+}
+
+/// The entity whose tooltip is due, and for how long it has been.
+#[derive(Resource, Default, Debug)]
+struct TooltipState {
+    owner: Option<(Entity, TooltipSource)>,
+    elapsed: f32,
+    /// Set by a click; the tooltip stays hidden until another entity takes over.
+    dismissed: bool,
+}
+
+#[derive(Component)]
+struct TooltipBox;
+
+fn spawn_tooltip_box(mut commands: Commands, ui_assets: Res<UIAssets>, ui_style: Res<UiStyle>) {
+    let font = ui_assets
+        .font
+        .clone()
+        .expect("Font should be loaded already");
+    commands.spawn((
+        TooltipBox,
+        Name::new("Tooltip"),
+        Node {
+            display: Display::None,
+            position_type: PositionType::Absolute,
+            max_width: Val::Px(400.0),
+            border: UiRect::all(Val::Px(ui_style.borders.normal)),
+            padding: UiRect::all(Val::Px(ui_style.spacing.small)),
+            ..Default::default()
+        },
+        StyleColor::HoverBack.background(&ui_style),
+        StyleColor::HoverFore.border(&ui_style),
+        GlobalZIndex(TOOLTIP_Z_INDEX),
+        Pickable::IGNORE,
+        children![(
+            Text::default(),
+            StyleColor::HoverFore.text(&ui_style),
+            TextFont {
+                font,
+                font_size: ui_style.font_sizes.caption,
+                ..Default::default()
+            },
+            Pickable::IGNORE,
+        )],
+    ));
+}
+
+/// The closest entity with a [`Tooltip`] from `entity` upwards. Popups belong to
+/// their owner in the hierarchy, but they don't show the owner's tooltip.
+fn tooltip_owner(
+    entity: Entity,
+    tooltips: &Query<(), With<Tooltip>>,
+    popups: &Query<(), With<Popup>>,
+    parent_query: &Query<&ChildOf>,
+) -> Option<Entity> {
+    std::iter::once(entity)
+        .chain(parent_query.iter_ancestors(entity))
+        .take_while(|e| !popups.contains(*e))
+        .find(|e| tooltips.contains(*e))
+}
+
+/// The hovered tooltip owner comes first, the focused one second.
+fn track_tooltip_owner(
+    time: Res<Time>,
+    hover_map: Res<HoverMap>,
+    focus: Res<InputFocus>,
+    tooltips: Query<(), With<Tooltip>>,
+    popups: Query<(), With<Popup>>,
+    parent_query: Query<&ChildOf>,
+    mut state: ResMut<TooltipState>,
+) {
+    let hovered = hover_map
+        .get(&PointerId::Mouse)
+        .into_iter()
+        .flat_map(|hits| hits.keys())
+        .find_map(|e| tooltip_owner(*e, &tooltips, &popups, &parent_query))
+        .map(|e| (e, TooltipSource::Pointer));
+    let focused = focus
+        .0
+        .and_then(|e| tooltip_owner(e, &tooltips, &popups, &parent_query))
+        .map(|e| (e, TooltipSource::Focus));
+    let owner = hovered.or(focused);
+    if state.owner.map(|(e, _)| e) != owner.map(|(e, _)| e) {
+        *state = TooltipState {
+            owner,
+            ..Default::default()
+        };
+    } else {
+        state.owner = owner;
+        state.elapsed += time.delta_secs();
+    }
+}
+
+fn dismiss_tooltip_on_press(trigger: Trigger<Pointer<Pressed>>, mut state: ResMut<TooltipState>) {
+    if trigger.target() == trigger.event().target && state.owner.is_some() {
+        state.dismissed = true;
+    }
+}
+
+/// Shows the due tooltip below the pointer or its focused owner, kept inside the window.
+#[allow(clippy::type_complexity)]
+fn update_tooltip_box(
+    state: Res<TooltipState>,
+    options: Res<TooltipOptions>,
+    tooltips: Query<(&Tooltip, &ComputedNode, &GlobalTransform)>,
+    tooltip_box: Single<
+        (&mut Node, &ComputedNode, &ComputedNodeTarget, &Children),
+        With<TooltipBox>,
+    >,
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut text_query: Query<&mut Text>,
+) {
+    let (mut node, computed, target, children) = tooltip_box.into_inner();
+    let shown = state
+        .owner
+        .filter(|_| !state.dismissed && state.elapsed >= options.delay)
+        .and_then(|(e, source)| tooltips.get(e).ok().map(|t| (t, source)));
+    let Some(((tooltip, owner_node, owner_transform), source)) = shown else {
+        if node.display != Display::None {
+            node.display = Display::None;
+        }
+        return;
+    };
+    set_box_text(children, &mut text_query, tooltip.0.clone());
+    let anchor = match (source, window.physical_cursor_position()) {
+        (TooltipSource::Pointer, Some(cursor)) => {
+            Rect::from_corners(cursor, cursor + CURSOR_SIZE * window.scale_factor())
+        }
+        _ => Rect::from_center_size(owner_transform.translation().truncate(), owner_node.size()),
+    };
+    let placement = place_popup(anchor, computed.size(), target.physical_size().as_vec2());
+    let position = (anchor.min + placement.offset) * computed.inverse_scale_factor();
+    if node.display != Display::Flex
+        || node.left != Val::Px(position.x)
+        || node.top != Val::Px(position.y)
+    {
+        node.display = Display::Flex;
+        node.left = Val::Px(position.x);
+        node.top = Val::Px(position.y);
+    }
+}
+
+pub struct TooltipPlugin;
+
+impl Plugin for TooltipPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TooltipOptions>()
+            .init_resource::<TooltipState>()
+            .add_observer(dismiss_tooltip_on_press)
+            .add_systems(Startup, spawn_tooltip_box)
+            .add_systems(Update, (track_tooltip_owner, update_tooltip_box).chain());
+    }
+}
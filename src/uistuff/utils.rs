@@ -3,6 +3,7 @@ use std::path::Path;
 use crate::uistuff::config::*;
 use bevy::{
     ecs::relationship::{RelatedSpawnerCommands, Relationship},
+    input_focus::{InputDispatchPlugin, InputFocus},
     prelude::*,
 };
 
@@ -48,63 +49,256 @@ fn load_assets(fonts: Res<AssetServer>, mut assets: ResMut<UIAssets>) {
     //assets.icon_font = Some(iconfont);
 }
 
-#[derive(Component, Debug)]
-#[require(Interaction)]
-pub struct ChangeColorOnHover {
-    pub normal_color: BackgroundForeground,
-    pub hover_color: BackgroundForeground,
+/// Rectangle of a laid out UI node in logical window pixels,
+/// the same space pointer locations are reported in.
+pub fn node_logical_rect(
+    transform: &GlobalTransform,
+    node: &ComputedNode,
+    window_scale_factor: f32,
+) -> Rect {
+    Rect::from_center_size(
+        transform.translation().truncate() / window_scale_factor,
+        node.size() / window_scale_factor,
+    )
 }
 
-impl From<ButtonStyle> for ChangeColorOnHover {
-    fn from(value: ButtonStyle) -> Self {
-        Self {
-            normal_color: value.normal_colors,
-            hover_color: value.hover_colors,
+/// Replaces the text shown by a box spawned with `text_box` or `button_box`.
+pub fn set_box_text(
+    children: &Children,
+    text_query: &mut Query<&mut Text>,
+    text: impl Into<String>,
+) {
+    let text = text.into();
+    for child in children {
+        if let Ok(mut child_text) = text_query.get_mut(*child) {
+            child_text.set_if_neq(Text(text.clone()));
         }
     }
 }
 
-pub fn change_color_on_hover(
-    mut comms: Commands,
-    backgr: Query<
-        (
-            &Children,
-            &ChangeColorOnHover,
-            &mut BackgroundColor,
-            &Interaction,
-        ),
-        Changed<Interaction>,
-    >,
+/// Draws a widget in its disabled colours.
+#[derive(Component, Default, Debug)]
+pub struct Disabled;
+
+/// Draws a widget in its selected colours, like the header of the active tab.
+#[derive(Component, Default, Debug)]
+pub struct Selected;
+
+/// Picks the [`VisualState`] of widgets from their interaction, the keyboard focus
+/// and their [`Disabled`] and [`Selected`] markers. Disabled wins over everything,
+/// then pressed, hovered, selected and focused.
+#[allow(clippy::type_complexity)]
+pub fn update_visual_states(
+    focus: Res<InputFocus>,
+    widgets: Query<(
+        Entity,
+        &Interaction,
+        Has<Disabled>,
+        Has<Selected>,
+        &mut VisualState,
+    )>,
 ) {
-    for (children, colors, mut col, int) in backgr {
-        let text_color = if matches!(int, Interaction::Hovered) {
-            colors.hover_color.fore_color
-        } else {
-            colors.normal_color.fore_color
+    for (entity, interaction, disabled, selected, mut state) in widgets {
+        let new_state = match interaction {
+            _ if disabled => VisualState::Disabled,
+            Interaction::Pressed => VisualState::Pressed,
+            Interaction::Hovered => VisualState::Hovered,
+            Interaction::None if selected => VisualState::Selected,
+            Interaction::None if focus.0 == Some(entity) => VisualState::Focused,
+            Interaction::None => VisualState::Normal,
         };
-        for child in children.iter() {
-            comms
-                .entity(child)
-                .entry::<TextColor>()
-                .and_modify(move |mut ent| {
-                    //ent.0 = colors.hover_color.fore_color;
-                    ent.0 = text_color;
-                });
+        state.set_if_neq(new_state);
+    }
+}
+
+/// Paints widgets with a [`StyleRole`] in the colours of their [`VisualState`]
+/// whenever the state or the [`UiStyle`] changes, and repaints styled colours
+/// when the style changes.
+///
+/// Systems that tint widgets on top of their style run after this one.
+#[allow(clippy::type_complexity)]
+pub fn restyle_widgets(
+    ui_style: Res<UiStyle>,
+    roles: Query<(
+        &StyleRole,
+        Option<Ref<VisualState>>,
+        &mut BackgroundColor,
+        &mut BorderColor,
+        Option<&Children>,
+    )>,
+    mut role_texts: Query<&mut TextColor, Without<StyledText>>,
+    backgrounds: Query<(&StyledBackground, &mut BackgroundColor), Without<StyleRole>>,
+    borders: Query<(&StyledBorder, &mut BorderColor), Without<StyleRole>>,
+    texts: Query<(&StyledText, &mut TextColor)>,
+) {
+    for (role, state, mut background, mut border, children) in roles {
+        if !ui_style.is_changed() && !state.as_ref().is_some_and(|s| s.is_changed()) {
+            continue;
         }
-        match int {
-            Interaction::Hovered => col.0 = colors.hover_color.back_color,
-            Interaction::None => col.0 = colors.normal_color.back_color,
-            _ => (),
+        let colors = role
+            .colors(&ui_style)
+            .colors(state.map_or(VisualState::Normal, |s| *s));
+        background.set_if_neq(BackgroundColor(colors.back_color));
+        border.set_if_neq(BorderColor(colors.border_color));
+        let mut iter = role_texts.iter_many_mut(children.into_iter().flatten());
+        while let Some(mut text_color) = iter.fetch_next() {
+            text_color.set_if_neq(TextColor(colors.fore_color));
         }
     }
+    if !ui_style.is_changed() {
+        return;
+    }
+    for (styled, mut background) in backgrounds {
+        background.set_if_neq(BackgroundColor(styled.0.get(&ui_style)));
+    }
+    for (styled, mut border) in borders {
+        border.set_if_neq(BorderColor(styled.0.get(&ui_style)));
+    }
+    for (styled, mut text_color) in texts {
+        text_color.set_if_neq(TextColor(styled.0.get(&ui_style)));
+    }
+}
+
+/// Widgets that take keyboard focus when pressed.
+/// The focused widget is drawn in its focused colours, or with an outline
+/// when it has no [`VisualState`].
+#[derive(Component, Default, Debug)]
+#[require(Outline)]
+pub struct Focusable;
+
+/// Focuses the innermost [`Focusable`] under the pointer, or clears the focus
+/// when something else was pressed.
+fn focus_on_press(
+    trigger: Trigger<Pointer<Pressed>>,
+    focusable: Query<(), With<Focusable>>,
+    parent_query: Query<&ChildOf>,
+    mut focus: ResMut<InputFocus>,
+) {
+    let pressed = trigger.event().target;
+    if trigger.target() != pressed {
+        return;
+    }
+    let target = std::iter::once(pressed)
+        .chain(parent_query.iter_ancestors(pressed))
+        .find(|e| focusable.contains(*e));
+    if focus.0 != target {
+        focus.0 = target;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_focus_outline(
+    focus: Res<InputFocus>,
+    query: Query<(Entity, &mut Outline), (With<Focusable>, Without<VisualState>)>,
+    ui_style: Res<UiStyle>,
+) {
+    for (entity, mut outline) in query {
+        let color = if focus.0 == Some(entity) {
+            ui_style.button_style.focused_colors.border_color
+        } else {
+            Color::NONE
+        };
+        outline.set_if_neq(Outline::new(
+            Val::Px(ui_style.borders.normal),
+            Val::Px(ui_style.borders.thin),
+            color,
+        ));
+    }
 }
 
 pub struct UiUtilsPlugin;
 
 impl Plugin for UiUtilsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(PreStartup, load_assets)
+        app.add_plugins(InputDispatchPlugin)
+            .add_systems(PreStartup, load_assets)
             .init_resource::<UIAssets>()
-            .add_systems(Update, change_color_on_hover);
+            .add_observer(focus_on_press)
+            .add_systems(
+                Update,
+                (
+                    update_focus_outline,
+                    (update_visual_states, restyle_widgets).chain(),
+                ),
+            );
+    }
+}
+
+/// Input for headless tests of widget observers.
+#[cfg(test)]
+pub(crate) mod test_input {
+    use std::time::Duration;
+
+    use bevy::{
+        input::{
+            ButtonState,
+            gamepad::GamepadButtonChangedEvent,
+            keyboard::{Key, KeyboardInput, NativeKey},
+            mouse::MouseWheel,
+        },
+        input_focus::InputDispatchPlugin,
+        picking::{
+            backend::HitData,
+            pointer::{Location, PointerId},
+        },
+        prelude::*,
+        render::camera::RenderTarget,
+        window::PrimaryWindow,
+    };
+
+    /// Adds a primary window and the dispatch of keyboard and gamepad input
+    /// to the focused entity, or to the window when nothing is focused.
+    pub fn add_input_dispatch(app: &mut App) -> Entity {
+        app.add_plugins(InputDispatchPlugin)
+            .add_event::<KeyboardInput>()
+            .add_event::<GamepadButtonChangedEvent>()
+            .add_event::<MouseWheel>();
+        app.world_mut()
+            .spawn((Window::default(), PrimaryWindow))
+            .id()
+    }
+
+    /// Presses `key_code` and runs one update to dispatch it.
+    pub fn press_key(app: &mut App, window: Entity, key_code: KeyCode) {
+        app.world_mut().send_event(KeyboardInput {
+            key_code,
+            logical_key: Key::Unidentified(NativeKey::Unidentified),
+            state: ButtonState::Pressed,
+            text: None,
+            repeat: false,
+            window,
+        });
+        app.update();
+    }
+
+    /// Presses `button` on a gamepad and runs one update to dispatch it.
+    pub fn press_gamepad_button(app: &mut App, button: GamepadButton) {
+        app.world_mut().send_event(GamepadButtonChangedEvent {
+            entity: Entity::PLACEHOLDER,
+            button,
+            state: ButtonState::Pressed,
+            value: 1.0,
+        });
+        app.update();
+    }
+
+    /// Triggers a primary click on `target`, bubbling up like a real one.
+    pub fn click(app: &mut App, target: Entity) {
+        let location = Location {
+            target: RenderTarget::Image(Handle::default().into())
+                .normalize(None)
+                .expect("Image targets always normalize"),
+            position: Vec2::ZERO,
+        };
+        let click = Click {
+            button: PointerButton::Primary,
+            hit: HitData::new(Entity::PLACEHOLDER, 0.0, None, None),
+            duration: Duration::ZERO,
+        };
+        app.world_mut().trigger_targets(
+            Pointer::new(PointerId::Mouse, location, target, click),
+            target,
+        );
+        app.world_mut().flush();
     }
 }
@@ -0,0 +1,903 @@
+use crate::uistuff::utils::*;
+use bevy::{
+    ecs::relationship::RelatedSpawnerCommands,
+    prelude::*,
+    window::{MonitorSelection, PresentMode, PrimaryWindow, VideoModeSelection, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::UiStyle,
+    modal::{ModalBody, ModalDialog, ModalResponse, spawn_modal},
+    persistence::SaveSettings,
+    radio::{RadioChanged, RadioGroup, RadioLayout, spawn_radio_group},
+    searchable_combobox::{FilterMode, spawn_searchable_combobox},
+    settings::{ComboboxChanged, DropdownMenu, DropdownMenuItem, SelectedIndex, spawn_combobox},
+    spin_box::{SpinBox, SpinBoxChanged, spawn_spin_box},
+    staging::{Modified, RevertField, Staged, settings_row},
+    tooltip::Tooltip,
+};
+
+/// Resolutions offered on the video settings page.
+pub const RESOLUTIONS: [(u32, u32); 6] = [
+    (1024, 768),
+    (1280, 720),
+    (1366, 768),
+    (1600, 900),
+    (1920, 1080),
+    (2560, 1440),
+];
+
+/// The UI scale is typed in percent, within this range and in steps of [`UI_SCALE_STEP`].
+const UI_SCALE_RANGE: (i32, i32) = (50, 200);
+const UI_SCALE_STEP: f32 = 25.0;
+
+/// How long the player has to confirm new video settings before they are reverted.
+const CONFIRMATION_SECONDS: f32 = 15.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowModeSetting {
+    #[default]
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl WindowModeSetting {
+    const ALL: [WindowModeSetting; 3] = [Self::Windowed, Self::Borderless, Self::Fullscreen];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Windowed => "Windowed",
+            Self::Borderless => "Borderless",
+            Self::Fullscreen => "Fullscreen",
+        }
+    }
+}
+
+impl From<WindowModeSetting> for WindowMode {
+    fn from(value: WindowModeSetting) -> Self {
+        match value {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::Borderless => {
+                WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+            }
+            WindowModeSetting::Fullscreen => {
+                WindowMode::Fullscreen(MonitorSelection::Current, VideoModeSelection::Current)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresentModeSetting {
+    #[default]
+    VSync,
+    Adaptive,
+    Off,
+}
+
+impl PresentModeSetting {
+    const ALL: [PresentModeSetting; 3] = [Self::VSync, Self::Adaptive, Self::Off];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::VSync => "VSync on",
+            Self::Adaptive => "Adaptive",
+            Self::Off => "VSync off",
+        }
+    }
+}
+
+impl From<PresentModeSetting> for PresentMode {
+    fn from(value: PresentModeSetting) -> Self {
+        match value {
+            PresentModeSetting::VSync => PresentMode::AutoVsync,
+            PresentModeSetting::Adaptive => PresentMode::FifoRelaxed,
+            PresentModeSetting::Off => PresentMode::AutoNoVsync,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MsaaSetting {
+    Off,
+    Sample2,
+    #[default]
+    Sample4,
+    Sample8,
+}
+
+impl MsaaSetting {
+    const ALL: [MsaaSetting; 4] = [Self::Off, Self::Sample2, Self::Sample4, Self::Sample8];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Sample2 => "2x",
+            Self::Sample4 => "4x",
+            Self::Sample8 => "8x",
+        }
+    }
+}
+
+impl From<MsaaSetting> for Msaa {
+    fn from(value: MsaaSetting) -> Self {
+        match value {
+            MsaaSetting::Off => Msaa::Off,
+            MsaaSetting::Sample2 => Msaa::Sample2,
+            MsaaSetting::Sample4 => Msaa::Sample4,
+            MsaaSetting::Sample8 => Msaa::Sample8,
+        }
+    }
+}
+
+/// Whether the interface smooths the edges of its rounded corners and borders.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UiAntiAliasSetting {
+    #[default]
+    On,
+    Off,
+}
+
+impl UiAntiAliasSetting {
+    const ALL: [UiAntiAliasSetting; 2] = [Self::On, Self::Off];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::On => "On",
+            Self::Off => "Off",
+        }
+    }
+}
+
+impl From<UiAntiAliasSetting> for UiAntiAlias {
+    fn from(value: UiAntiAliasSetting) -> Self {
+        match value {
+            UiAntiAliasSetting::On => UiAntiAlias::On,
+            UiAntiAliasSetting::Off => UiAntiAlias::Off,
+        }
+    }
+}
+
+/// A named group of graphics settings. Changing any of them by hand makes it `Custom`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QualityPreset {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Custom,
+}
+
+impl QualityPreset {
+    const ALL: [QualityPreset; 4] = [Self::Low, Self::Medium, Self::High, Self::Custom];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Low => "Low",
+            Self::Medium => "Medium",
+            Self::High => "High",
+            Self::Custom => "Custom",
+        }
+    }
+
+    /// The values the preset stands for, `None` for `Custom`.
+    fn values(self) -> Option<(MsaaSetting, UiAntiAliasSetting)> {
+        match self {
+            Self::Low => Some((MsaaSetting::Off, UiAntiAliasSetting::Off)),
+            Self::Medium => Some((MsaaSetting::Sample4, UiAntiAliasSetting::On)),
+            Self::High => Some((MsaaSetting::Sample8, UiAntiAliasSetting::On)),
+            Self::Custom => None,
+        }
+    }
+
+    /// Selects the preset and sets its values. `Custom` keeps the current values.
+    pub fn apply(self, settings: &mut VideoSettings) {
+        settings.quality = self;
+        if let Some((msaa, ui_anti_alias)) = self.values() {
+            settings.msaa = msaa;
+            settings.ui_anti_alias = ui_anti_alias;
+        }
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoSettings {
+    pub window_mode: WindowModeSetting,
+    pub resolution: (u32, u32),
+    pub present_mode: PresentModeSetting,
+    pub ui_scale: f32,
+    pub quality: QualityPreset,
+    pub msaa: MsaaSetting,
+    pub ui_anti_alias: UiAntiAliasSetting,
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        Self {
+            window_mode: WindowModeSetting::Windowed,
+            resolution: (1280, 720),
+            present_mode: PresentModeSetting::VSync,
+            ui_scale: 1.0,
+            quality: QualityPreset::Medium,
+            msaa: MsaaSetting::Sample4,
+            ui_anti_alias: UiAntiAliasSetting::On,
+        }
+    }
+}
+
+/// Marks a combobox that edits one field of [`VideoSettings`].
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoSettingField {
+    WindowMode,
+    Resolution,
+    PresentMode,
+    UiScale,
+    Quality,
+    Msaa,
+    UiAntiAlias,
+}
+
+impl VideoSettingField {
+    const ALL: [VideoSettingField; 7] = [
+        Self::WindowMode,
+        Self::Resolution,
+        Self::PresentMode,
+        Self::UiScale,
+        Self::Quality,
+        Self::Msaa,
+        Self::UiAntiAlias,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::WindowMode => "Window mode",
+            Self::Resolution => "Resolution",
+            Self::PresentMode => "Present mode",
+            Self::UiScale => "UI scale",
+            Self::Quality => "Graphics quality",
+            Self::Msaa => "Anti-aliasing",
+            Self::UiAntiAlias => "Smooth UI edges",
+        }
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            Self::WindowMode => "Windowed, borderless fullscreen or exclusive fullscreen",
+            Self::Resolution => "Size of the window in windowed mode",
+            Self::PresentMode => {
+                "Vertical sync limits the frame rate to the display's refresh rate"
+            }
+            Self::UiScale => "Makes the whole interface bigger or smaller",
+            Self::Quality => "Sets anti-aliasing and UI edge smoothing at once",
+            Self::Msaa => "Multisample anti-aliasing smooths jagged edges",
+            Self::UiAntiAlias => "Smooths the edges of rounded corners and borders",
+        }
+    }
+
+    fn options(self) -> Vec<String> {
+        match self {
+            Self::WindowMode => WindowModeSetting::ALL
+                .iter()
+                .map(|m| m.label().to_string())
+                .collect(),
+            Self::Resolution => RESOLUTIONS
+                .iter()
+                .map(|(w, h)| format!("{w} x {h}"))
+                .collect(),
+            Self::PresentMode => PresentModeSetting::ALL
+                .iter()
+                .map(|m| m.label().to_string())
+                .collect(),
+            Self::UiScale => Vec::new(),
+            Self::Quality => QualityPreset::ALL
+                .iter()
+                .map(|q| q.label().to_string())
+                .collect(),
+            Self::Msaa => MsaaSetting::ALL
+                .iter()
+                .map(|m| m.label().to_string())
+                .collect(),
+            Self::UiAntiAlias => UiAntiAliasSetting::ALL
+                .iter()
+                .map(|a| a.label().to_string())
+                .collect(),
+        }
+    }
+
+    /// Index of the option matching the current value, falling back to the first option
+    /// when the value isn't in the list (e.g. a hand-edited settings file).
+    /// The UI scale has no options, it is edited with a spin box.
+    fn selected_index(self, settings: &VideoSettings) -> usize {
+        let found = match self {
+            Self::WindowMode => WindowModeSetting::ALL
+                .iter()
+                .position(|m| *m == settings.window_mode),
+            Self::Resolution => RESOLUTIONS.iter().position(|r| *r == settings.resolution),
+            Self::PresentMode => PresentModeSetting::ALL
+                .iter()
+                .position(|m| *m == settings.present_mode),
+            Self::UiScale => None,
+            Self::Quality => QualityPreset::ALL
+                .iter()
+                .position(|q| *q == settings.quality),
+            Self::Msaa => MsaaSetting::ALL.iter().position(|m| *m == settings.msaa),
+            Self::UiAntiAlias => UiAntiAliasSetting::ALL
+                .iter()
+                .position(|a| *a == settings.ui_anti_alias),
+        };
+        found.unwrap_or(0)
+    }
+
+    /// Whether the field is one of the values set by a [`QualityPreset`].
+    fn in_quality_preset(self) -> bool {
+        matches!(self, Self::Msaa | Self::UiAntiAlias)
+    }
+
+    /// Sets the field to the option at `index`.
+    fn apply(self, settings: &mut VideoSettings, index: usize) {
+        match self {
+            Self::WindowMode => settings.window_mode = WindowModeSetting::ALL[index],
+            Self::Resolution => settings.resolution = RESOLUTIONS[index],
+            Self::PresentMode => settings.present_mode = PresentModeSetting::ALL[index],
+            Self::UiScale => (),
+            Self::Quality => QualityPreset::ALL[index].apply(settings),
+            Self::Msaa => settings.msaa = MsaaSetting::ALL[index],
+            Self::UiAntiAlias => settings.ui_anti_alias = UiAntiAliasSetting::ALL[index],
+        }
+    }
+
+    /// Whether the field has different values in `a` and `b`.
+    fn differs(self, a: &VideoSettings, b: &VideoSettings) -> bool {
+        match self {
+            Self::WindowMode => a.window_mode != b.window_mode,
+            Self::Resolution => a.resolution != b.resolution,
+            Self::PresentMode => a.present_mode != b.present_mode,
+            Self::UiScale => a.ui_scale != b.ui_scale,
+            Self::Quality => a.quality != b.quality,
+            Self::Msaa => a.msaa != b.msaa,
+            Self::UiAntiAlias => a.ui_anti_alias != b.ui_anti_alias,
+        }
+    }
+
+    /// Sets the field to its value in `from`. The quality also brings back the
+    /// values of its preset.
+    fn copy_value(self, from: &VideoSettings, settings: &mut VideoSettings) {
+        match self {
+            Self::WindowMode => settings.window_mode = from.window_mode,
+            Self::Resolution => settings.resolution = from.resolution,
+            Self::PresentMode => settings.present_mode = from.present_mode,
+            Self::UiScale => settings.ui_scale = from.ui_scale,
+            Self::Quality => from.quality.apply(settings),
+            Self::Msaa => settings.msaa = from.msaa,
+            Self::UiAntiAlias => settings.ui_anti_alias = from.ui_anti_alias,
+        }
+    }
+}
+
+/// The spin box editing the UI scale, in percent.
+fn ui_scale_spin_box(ui_scale: f32) -> SpinBox {
+    let (min, max) = UI_SCALE_RANGE;
+    let spin_box = SpinBox::integer(min, max, 100).with_step(UI_SCALE_STEP);
+    SpinBox {
+        value: spin_box.constrain(ui_scale * 100.0),
+        ..spin_box
+    }
+}
+
+/// Sent when applying the settings page changed the video settings.
+/// The player then has to confirm them before they're saved.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct VideoSettingsApplied {
+    pub previous: VideoSettings,
+}
+
+struct PendingConfirmation {
+    previous: VideoSettings,
+    timer: Timer,
+}
+
+/// Video settings waiting for the player to confirm them.
+#[derive(Resource, Default)]
+struct PendingVideoConfirmation(Option<PendingConfirmation>);
+
+#[derive(Component)]
+struct ConfirmationDialog;
+
+/// Spawns one settings row per video option, starting at grid row `first_row`.
+pub fn spawn_video_settings(
+    parent: &mut RelatedSpawnerCommands<ChildOf>,
+    ui_assets: &UIAssets,
+    ui_style: &UiStyle,
+    settings: &VideoSettings,
+    first_row: i16,
+) {
+    let font = ui_assets
+        .font
+        .clone()
+        .expect("Font should be loaded already");
+    for (field, row) in VideoSettingField::ALL.into_iter().zip(first_row..) {
+        let modifier = settings_row(parent, field.label(), field, font.clone(), row, ui_style);
+        let extra = (field, Name::new(field.label()), Tooltip::new(field.hint()));
+        let menu = DropdownMenu {
+            items: field
+                .options()
+                .into_iter()
+                .map(|label| DropdownMenuItem { label })
+                .collect(),
+            ..Default::default()
+        };
+        let selected = field.selected_index(settings);
+        match field {
+            // Resolutions are easier to find by typing a part of them.
+            VideoSettingField::Resolution => {
+                spawn_searchable_combobox(
+                    &mut *parent,
+                    extra,
+                    menu,
+                    selected,
+                    FilterMode::Substring,
+                    modifier,
+                    ui_assets,
+                    ui_style,
+                );
+            }
+            VideoSettingField::WindowMode => {
+                spawn_radio_group(
+                    &mut *parent,
+                    extra,
+                    RadioGroup::new(field.options(), selected),
+                    RadioLayout::Horizontal,
+                    modifier,
+                    ui_assets,
+                    ui_style,
+                );
+            }
+            VideoSettingField::UiScale => {
+                spawn_spin_box(
+                    &mut *parent,
+                    extra,
+                    ui_scale_spin_box(settings.ui_scale),
+                    modifier,
+                    ui_assets,
+                    ui_style,
+                );
+            }
+            _ => {
+                spawn_combobox(
+                    &mut *parent,
+                    extra,
+                    menu,
+                    selected,
+                    modifier,
+                    ui_assets,
+                    ui_style,
+                );
+            }
+        }
+    }
+}
+
+fn apply_video_settings(
+    mut commands: Commands,
+    settings: Res<VideoSettings>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+    mut ui_scale: ResMut<UiScale>,
+    mut cameras: Query<(Entity, &mut Msaa), With<Camera>>,
+) {
+    let Ok(mut window) = window.single_mut() else {
+        return;
+    };
+    info!("Applying video settings {:?}", *settings);
+    window.mode = settings.window_mode.into();
+    window.present_mode = settings.present_mode.into();
+    let (width, height) = settings.resolution;
+    window.resolution.set(width as f32, height as f32);
+    ui_scale.0 = settings.ui_scale;
+    for (camera, mut msaa) in &mut cameras {
+        msaa.set_if_neq(settings.msaa.into());
+        commands
+            .entity(camera)
+            .insert(UiAntiAlias::from(settings.ui_anti_alias));
+    }
+}
+
+fn on_video_combobox_changed(
+    mut events: EventReader<ComboboxChanged>,
+    fields: Query<&VideoSettingField>,
+    mut staged: ResMut<Staged<VideoSettings>>,
+) {
+    for event in events.read() {
+        let Ok(field) = fields.get(event.combobox) else {
+            continue;
+        };
+        info!("{} set to {}", field.label(), event.label);
+        let mut new_settings = staged.0;
+        field.apply(&mut new_settings, event.index);
+        if field.in_quality_preset() && new_settings != staged.0 {
+            new_settings.quality = QualityPreset::Custom;
+        }
+        staged.set_if_neq(Staged(new_settings));
+    }
+}
+
+fn on_video_radio_changed(
+    mut events: EventReader<RadioChanged>,
+    fields: Query<&VideoSettingField>,
+    mut staged: ResMut<Staged<VideoSettings>>,
+) {
+    for event in events.read() {
+        let Ok(field) = fields.get(event.group) else {
+            continue;
+        };
+        let mut new_settings = staged.0;
+        field.apply(&mut new_settings, event.index);
+        staged.set_if_neq(Staged(new_settings));
+    }
+}
+
+fn on_video_spin_box_changed(
+    mut events: EventReader<SpinBoxChanged>,
+    fields: Query<&VideoSettingField>,
+    mut staged: ResMut<Staged<VideoSettings>>,
+) {
+    for event in events.read() {
+        let Ok(VideoSettingField::UiScale) = fields.get(event.spin_box) else {
+            continue;
+        };
+        let mut new_settings = staged.0;
+        new_settings.ui_scale = event.value / 100.0;
+        staged.set_if_neq(Staged(new_settings));
+    }
+}
+
+fn on_video_field_reverted(
+    mut events: EventReader<RevertField>,
+    fields: Query<&VideoSettingField>,
+    settings: Res<VideoSettings>,
+    mut staged: ResMut<Staged<VideoSettings>>,
+) {
+    for event in events.read() {
+        let Ok(field) = fields.get(event.entity) else {
+            continue;
+        };
+        let mut new_settings = staged.0;
+        if *field == VideoSettingField::Quality {
+            new_settings.quality = settings.quality;
+            new_settings.msaa = settings.msaa;
+            new_settings.ui_anti_alias = settings.ui_anti_alias;
+        } else {
+            field.copy_value(&settings, &mut new_settings);
+        }
+        // The preset only goes back too once all of its values are back.
+        if field.in_quality_preset() {
+            let restored = new_settings.msaa == settings.msaa
+                && new_settings.ui_anti_alias == settings.ui_anti_alias;
+            new_settings.quality = if restored {
+                settings.quality
+            } else {
+                QualityPreset::Custom
+            };
+        }
+        staged.set_if_neq(Staged(new_settings));
+    }
+}
+
+/// Applied video settings are kept for [`CONFIRMATION_SECONDS`] unless the player confirms them.
+fn start_video_confirmation(
+    mut events: EventReader<VideoSettingsApplied>,
+    mut pending: ResMut<PendingVideoConfirmation>,
+) {
+    for event in events.read() {
+        match pending.0 {
+            Some(ref mut confirmation) => confirmation.timer.reset(),
+            None => {
+                pending.0 = Some(PendingConfirmation {
+                    previous: event.previous,
+                    timer: Timer::from_seconds(CONFIRMATION_SECONDS, TimerMode::Once),
+                })
+            }
+        }
+    }
+}
+
+fn sync_video_widgets(
+    staged: Res<Staged<VideoSettings>>,
+    widgets: Query<(&VideoSettingField, &mut SelectedIndex)>,
+    radio_groups: Query<(&VideoSettingField, &mut RadioGroup)>,
+    spin_boxes: Query<&mut SpinBox, With<VideoSettingField>>,
+) {
+    for (field, mut selected) in widgets {
+        selected.set_if_neq(SelectedIndex(field.selected_index(&staged)));
+    }
+    for (field, mut group) in radio_groups {
+        let index = field.selected_index(&staged);
+        if group.selected != index {
+            group.selected = index;
+        }
+    }
+    for mut spin_box in spin_boxes {
+        let value = spin_box.constrain(staged.ui_scale * 100.0);
+        if spin_box.value != value {
+            spin_box.value = value;
+        }
+    }
+}
+
+fn update_video_modified(
+    settings: Res<VideoSettings>,
+    staged: Res<Staged<VideoSettings>>,
+    entries: Query<(&VideoSettingField, &mut Modified)>,
+) {
+    for (field, mut modified) in entries {
+        modified.set_if_neq(Modified(field.differs(&settings, &staged)));
+    }
+}
+
+fn countdown_text(timer: &Timer) -> String {
+    format!(
+        "Keep these settings?\nReverting in {} s",
+        timer.remaining_secs().ceil()
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_video_confirmation(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pending: ResMut<PendingVideoConfirmation>,
+    mut settings: ResMut<VideoSettings>,
+    mut staged: ResMut<Staged<VideoSettings>>,
+    mut save: EventWriter<SaveSettings>,
+    dialog: Query<Entity, With<ConfirmationDialog>>,
+    children_query: Query<&Children>,
+    bodies: Query<&Children, With<ModalBody>>,
+    mut text_query: Query<&mut Text>,
+    ui_assets: Res<UIAssets>,
+    ui_style: Res<UiStyle>,
+) {
+    let Some(ref mut confirmation) = pending.0 else {
+        return;
+    };
+    confirmation.timer.tick(time.delta());
+    if confirmation.timer.finished() {
+        info!("Video settings were not confirmed, reverting");
+        *settings = confirmation.previous;
+        staged.0 = confirmation.previous;
+        save.write(SaveSettings);
+        pending.0 = None;
+        for e in dialog {
+            commands.entity(e).despawn();
+        }
+        return;
+    }
+    if dialog.is_empty() {
+        spawn_confirmation_dialog(&mut commands, &ui_assets, &ui_style, &confirmation.timer);
+    }
+    for e in dialog {
+        for body in children_query.iter_descendants(e) {
+            if let Ok(children) = bodies.get(body) {
+                set_box_text(
+                    children,
+                    &mut text_query,
+                    countdown_text(&confirmation.timer),
+                );
+            }
+        }
+    }
+}
+
+fn spawn_confirmation_dialog(
+    commands: &mut Commands,
+    ui_assets: &UIAssets,
+    ui_style: &UiStyle,
+    timer: &Timer,
+) {
+    let dialog = ModalDialog::new("Video settings", countdown_text(timer))
+        .with_buttons(["Keep", "Revert"])
+        .with_escape_button(1);
+    let entity = spawn_modal(
+        commands,
+        (ConfirmationDialog, Name::new("VideoConfirmation")),
+        dialog,
+        ui_assets,
+        ui_style,
+    );
+    commands.entity(entity).observe(on_confirmation_response);
+}
+
+fn on_confirmation_response(
+    trigger: Trigger<ModalResponse>,
+    mut pending: ResMut<PendingVideoConfirmation>,
+    mut settings: ResMut<VideoSettings>,
+    mut staged: ResMut<Staged<VideoSettings>>,
+    mut save: EventWriter<SaveSettings>,
+) {
+    let Some(confirmation) = pending.0.take() else {
+        return;
+    };
+    // Keep is the first button, Revert the second.
+    if trigger.event().button == 1 {
+        *settings = confirmation.previous;
+        staged.0 = confirmation.previous;
+    }
+    save.write(SaveSettings);
+}
+
+pub struct VideoSettingsPlugin;
+
+impl Plugin for VideoSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingVideoConfirmation>()
+            .add_event::<VideoSettingsApplied>()
+            .add_systems(
+                Update,
+                (
+                    on_video_combobox_changed.run_if(on_event::<ComboboxChanged>),
+                    on_video_radio_changed.run_if(on_event::<RadioChanged>),
+                    on_video_spin_box_changed.run_if(on_event::<SpinBoxChanged>),
+                    on_video_field_reverted.run_if(on_event::<RevertField>),
+                    start_video_confirmation.run_if(on_event::<VideoSettingsApplied>),
+                    apply_video_settings.run_if(resource_changed::<VideoSettings>),
+                    sync_video_widgets.run_if(resource_changed::<Staged<VideoSettings>>),
+                    update_video_modified.run_if(
+                        resource_changed::<VideoSettings>
+                            .or(resource_changed::<Staged<VideoSettings>>),
+                    ),
+                    update_video_confirmation,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selected_index_matches_values() {
+        let settings = VideoSettings {
+            window_mode: WindowModeSetting::Fullscreen,
+            resolution: (1920, 1080),
+            present_mode: PresentModeSetting::Off,
+            ..Default::default()
+        };
+        assert_eq!(VideoSettingField::WindowMode.selected_index(&settings), 2);
+        assert_eq!(VideoSettingField::Resolution.selected_index(&settings), 4);
+        assert_eq!(VideoSettingField::PresentMode.selected_index(&settings), 2);
+    }
+
+    #[test]
+    fn selected_index_falls_back_to_first_option() {
+        let settings = VideoSettings {
+            resolution: (123, 456),
+            ..Default::default()
+        };
+        assert_eq!(VideoSettingField::Resolution.selected_index(&settings), 0);
+    }
+
+    #[test]
+    fn ui_scale_is_compared_and_copied_by_value() {
+        let settings = VideoSettings::default();
+        let mut staged = VideoSettings {
+            ui_scale: 1.75,
+            ..settings
+        };
+        assert!(VideoSettingField::UiScale.differs(&settings, &staged));
+        assert!(!VideoSettingField::Resolution.differs(&settings, &staged));
+        VideoSettingField::UiScale.copy_value(&settings, &mut staged);
+        assert_eq!(staged, settings);
+    }
+
+    #[test]
+    fn unlisted_resolutions_are_compared_and_copied_by_value() {
+        let settings = VideoSettings {
+            resolution: (123, 456),
+            ..Default::default()
+        };
+        let mut staged = VideoSettings {
+            resolution: RESOLUTIONS[0],
+            ..settings
+        };
+        assert!(VideoSettingField::Resolution.differs(&settings, &staged));
+        VideoSettingField::Resolution.copy_value(&settings, &mut staged);
+        assert_eq!(staged.resolution, (123, 456));
+    }
+
+    #[test]
+    fn ui_scale_spin_box_snaps_to_steps() {
+        assert_eq!(ui_scale_spin_box(1.0).value, 100.0);
+        assert_eq!(ui_scale_spin_box(1.3).value, 125.0);
+        assert_eq!(ui_scale_spin_box(5.0).value, 200.0);
+    }
+
+    #[test]
+    fn apply_round_trips_through_selected_index() {
+        for field in VideoSettingField::ALL {
+            for index in 0..field.options().len() {
+                let mut settings = VideoSettings::default();
+                field.apply(&mut settings, index);
+                assert_eq!(field.selected_index(&settings), index, "{field:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn presets_set_their_values() {
+        let mut settings = VideoSettings::default();
+        QualityPreset::Low.apply(&mut settings);
+        assert_eq!(settings.quality, QualityPreset::Low);
+        assert_eq!(settings.msaa, MsaaSetting::Off);
+        assert_eq!(settings.ui_anti_alias, UiAntiAliasSetting::Off);
+        QualityPreset::High.apply(&mut settings);
+        assert_eq!(settings.msaa, MsaaSetting::Sample8);
+        assert_eq!(settings.ui_anti_alias, UiAntiAliasSetting::On);
+    }
+
+    #[test]
+    fn custom_preset_keeps_values() {
+        let mut settings = VideoSettings::default();
+        QualityPreset::Low.apply(&mut settings);
+        QualityPreset::Custom.apply(&mut settings);
+        assert_eq!(settings.quality, QualityPreset::Custom);
+        assert_eq!(settings.msaa, MsaaSetting::Off);
+        assert_eq!(settings.ui_anti_alias, UiAntiAliasSetting::Off);
+    }
+
+    fn combobox_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_event::<ComboboxChanged>()
+            .insert_resource(Staged(VideoSettings::default()))
+            .add_systems(
+                Update,
+                on_video_combobox_changed.run_if(on_event::<ComboboxChanged>),
+            );
+        app
+    }
+
+    fn pick(app: &mut App, field: VideoSettingField, index: usize) {
+        let combobox = app.world_mut().spawn(field).id();
+        app.world_mut().send_event(ComboboxChanged {
+            combobox,
+            index,
+            label: field.options()[index].clone(),
+        });
+        app.update();
+    }
+
+    fn staged(app: &App) -> VideoSettings {
+        app.world().resource::<Staged<VideoSettings>>().0
+    }
+
+    #[test]
+    fn changing_a_preset_value_switches_to_custom() {
+        let mut app = combobox_app();
+        pick(&mut app, VideoSettingField::Msaa, 0);
+        assert_eq!(staged(&app).msaa, MsaaSetting::Off);
+        assert_eq!(staged(&app).quality, QualityPreset::Custom);
+    }
+
+    #[test]
+    fn picking_the_current_value_keeps_the_preset() {
+        let mut app = combobox_app();
+        let index = VideoSettingField::UiAntiAlias.selected_index(&VideoSettings::default());
+        pick(&mut app, VideoSettingField::UiAntiAlias, index);
+        assert_eq!(staged(&app).quality, QualityPreset::Medium);
+    }
+
+    #[test]
+    fn picking_a_preset_sets_its_values() {
+        let mut app = combobox_app();
+        pick(&mut app, VideoSettingField::Msaa, 1);
+        pick(&mut app, VideoSettingField::Quality, 2);
+        assert_eq!(staged(&app).quality, QualityPreset::High);
+        assert_eq!(staged(&app).msaa, MsaaSetting::Sample8);
+        // Fields outside the preset don't affect it.
+        pick(&mut app, VideoSettingField::PresentMode, 2);
+        assert_eq!(staged(&app).quality, QualityPreset::High);
+    }
+}